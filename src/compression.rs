@@ -0,0 +1,215 @@
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+
+use error::{GeoTiffError, Result};
+use lowlevel::Compression;
+use reader::MAX_PREALLOCATION;
+
+/// Decompresses the raw bytes of a single strip or tile.
+///
+/// `expected_len` is the number of bytes the strip or tile holds once decompressed. Decoders use
+/// it to size their output, and to stop early if the compressed stream contains trailing data.
+pub fn decompress(
+    compression: &Compression,
+    data: Vec<u8>,
+    expected_len: usize,
+) -> Result<Vec<u8>> {
+    match *compression {
+        Compression::None => Ok(data),
+        Compression::Lzw => decode_lzw(&data, expected_len),
+        Compression::AdobeDeflate | Compression::Deflate => decode_deflate(&data, expected_len),
+        Compression::PackBits => decode_packbits(&data, expected_len),
+        c => Err(GeoTiffError::UnsupportedCompression(c as u16)),
+    }
+}
+
+/// The most bytes that `len` bytes compressed with `compression` can decompress into, which allows
+/// to reject strips and tiles whose size can't possibly be stored in their bytes.
+pub fn max_decompressed_len(compression: &Compression, len: usize) -> usize {
+    match *compression {
+        Compression::None => len,
+        // A code of at least 9 bits decodes to a string of at most 4096 bytes.
+        Compression::Lzw => len.saturating_mul(4096),
+        // A match of at most 258 bytes takes at least 2 bits.
+        Compression::AdobeDeflate | Compression::Deflate => len.saturating_mul(1032),
+        // A run of at most 128 bytes takes 2 bytes.
+        Compression::PackBits => len.saturating_mul(64),
+        _ => usize::MAX,
+    }
+}
+
+const LZW_CLEAR_CODE: usize = 256;
+const LZW_EOI_CODE: usize = 257;
+const LZW_FIRST_CODE: usize = 258;
+const LZW_MAX_CODE_WIDTH: u32 = 12;
+
+/// Decodes TIFF flavoured LZW data (compression 5).
+///
+/// Codes start out 9 bits wide and grow up to 12 bits. Current files pack codes MSB-first and
+/// switch to the next code width one code early. Files written by very old libraries (before
+/// TIFF 6.0) pack codes LSB-first and switch without the early change; they are recognised the
+/// same way libtiff does it, i.e., by the bit pattern of the leading clear code.
+///
+/// Instead of keeping a copy of every string in the code table, each entry references the place
+/// in the output where the string was first written.
+fn decode_lzw(input: &[u8], expected_len: usize) -> Result<Vec<u8>> {
+    let old_style = input.len() >= 2 && input[0] == 0 && input[1] & 0x01 != 0;
+    let mut bits = LzwBitReader::new(input, old_style);
+    let mut out = Vec::with_capacity(expected_len.min(MAX_PREALLOCATION as usize));
+    // (start, length) within `out` of the strings for the codes from LZW_FIRST_CODE upwards.
+    let mut table: Vec<(usize, usize)> = Vec::with_capacity(4096 - LZW_FIRST_CODE);
+    let mut width = 9;
+    // (start, length) within `out` of the string decoded from the previous code.
+    let mut previous: Option<(usize, usize)> = None;
+
+    while out.len() < expected_len {
+        let code = match bits.read(width) {
+            Some(code) => code,
+            // Some writers omit the end of information code.
+            None => break,
+        };
+        if code == LZW_EOI_CODE {
+            break;
+        }
+        if code == LZW_CLEAR_CODE {
+            table.clear();
+            width = 9;
+            previous = None;
+            continue;
+        }
+
+        let start = out.len();
+        let next_code = LZW_FIRST_CODE + table.len();
+        let length = if code < 256 {
+            out.push(code as u8);
+            1
+        } else if code < next_code {
+            let (entry_start, entry_length) = table[code - LZW_FIRST_CODE];
+            out.extend_from_within(entry_start..entry_start + entry_length);
+            entry_length
+        } else if let (true, Some((prev_start, prev_length))) = (code == next_code, previous) {
+            // The code being defined right now: the previous string plus its own first byte.
+            out.extend_from_within(prev_start..prev_start + prev_length);
+            out.push(out[prev_start]);
+            prev_length + 1
+        } else {
+            return Err(GeoTiffError::CorruptData(format!(
+                "Invalid LZW code {}",
+                code
+            )));
+        };
+
+        if let Some((prev_start, prev_length)) = previous {
+            // The new entry is the previous string plus the first byte of the current one. As the
+            // current string directly follows the previous one in the output, this is simply the
+            // previous string extended by one byte.
+            if next_code < 1 << LZW_MAX_CODE_WIDTH {
+                table.push((prev_start, prev_length + 1));
+            }
+            let next_code = LZW_FIRST_CODE + table.len();
+            let switch_at = if old_style {
+                1 << width
+            } else {
+                (1 << width) - 1
+            };
+            if next_code >= switch_at && width < LZW_MAX_CODE_WIDTH {
+                width += 1;
+            }
+        }
+        previous = Some((start, length));
+    }
+
+    out.truncate(expected_len);
+    Ok(out)
+}
+
+/// Decodes zlib wrapped deflate data (compression 8 and the older, but identical, 32946).
+fn decode_deflate(input: &[u8], expected_len: usize) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(expected_len.min(MAX_PREALLOCATION as usize));
+    ZlibDecoder::new(input)
+        .take(expected_len as u64)
+        .read_to_end(&mut out)
+        .map_err(|e| GeoTiffError::CorruptData(e.to_string()))?;
+    Ok(out)
+}
+
+/// Decodes PackBits run-length encoded data (compression 32773).
+///
+/// Each run starts with a signed header byte `n`: for 0 <= n <= 127 the next n + 1 bytes are
+/// copied literally, for -127 <= n <= -1 the next byte is repeated 1 - n times, and -128 is a
+/// no-op.
+fn decode_packbits(input: &[u8], expected_len: usize) -> Result<Vec<u8>> {
+    let truncated = || GeoTiffError::CorruptData("Truncated PackBits run".to_string());
+    let mut out = Vec::with_capacity(expected_len.min(MAX_PREALLOCATION as usize));
+    let mut position = 0;
+    while position < input.len() && out.len() < expected_len {
+        let header = input[position] as i8;
+        position += 1;
+        match header {
+            0..=127 => {
+                let count = header as usize + 1;
+                let literal = input
+                    .get(position..position + count)
+                    .ok_or_else(truncated)?;
+                out.extend_from_slice(literal);
+                position += count;
+            }
+            -127..=-1 => {
+                let byte = *input.get(position).ok_or_else(truncated)?;
+                out.resize(out.len() + (1 - header as isize) as usize, byte);
+                position += 1;
+            }
+            -128 => {}
+        }
+    }
+    out.truncate(expected_len);
+    Ok(out)
+}
+
+/// Reads variable width codes from a byte slice, either MSB-first or LSB-first.
+struct LzwBitReader<'a> {
+    input: &'a [u8],
+    position: usize,
+    buffer: u32,
+    buffered_bits: u32,
+    lsb_first: bool,
+}
+
+impl<'a> LzwBitReader<'a> {
+    fn new(input: &'a [u8], lsb_first: bool) -> LzwBitReader<'a> {
+        LzwBitReader {
+            input,
+            position: 0,
+            buffer: 0,
+            buffered_bits: 0,
+            lsb_first,
+        }
+    }
+
+    /// Reads the next code of `width` bits, or `None` if the input is exhausted.
+    fn read(&mut self, width: u32) -> Option<usize> {
+        while self.buffered_bits < width {
+            let byte = *self.input.get(self.position)? as u32;
+            self.position += 1;
+            if self.lsb_first {
+                self.buffer |= byte << self.buffered_bits;
+            } else {
+                self.buffer = (self.buffer << 8) | byte;
+            }
+            self.buffered_bits += 8;
+        }
+        let mask = (1 << width) - 1;
+        let code = if self.lsb_first {
+            let code = self.buffer & mask;
+            self.buffer >>= width;
+            code
+        } else {
+            let code = (self.buffer >> (self.buffered_bits - width)) & mask;
+            self.buffer &= (1 << (self.buffered_bits - width)) - 1;
+            code
+        };
+        self.buffered_bits -= width;
+        Some(code as usize)
+    }
+}
@@ -0,0 +1,1026 @@
+use std::fmt::Write;
+
+use error::{GeoTiffError, Result};
+use lowlevel::TIFFTag;
+use tiff::GeoKey;
+
+/// The value of GeoKeys that indicates a user-defined code, i.e., one whose definition is given
+/// by further keys instead of an EPSG code.
+const USER_DEFINED: u16 = 32767;
+
+/// A unit of measure, with its conversion factor to the SI base unit (metre or radian).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unit {
+    pub name: String,
+    pub factor: f64,
+}
+
+impl Unit {
+    fn new(name: &str, factor: f64) -> Unit {
+        Unit {
+            name: name.to_string(),
+            factor,
+        }
+    }
+
+    pub fn metre() -> Unit {
+        Unit::new("metre", 1.0)
+    }
+
+    pub fn degree() -> Unit {
+        Unit::new("degree", std::f64::consts::PI / 180.0)
+    }
+
+    fn linear(code: u16) -> Option<Unit> {
+        match code {
+            9001 => Some(Unit::metre()),
+            9002 => Some(Unit::new("foot", 0.3048)),
+            9003 => Some(Unit::new("US survey foot", 1200.0 / 3937.0)),
+            9036 => Some(Unit::new("kilometre", 1000.0)),
+            _ => None,
+        }
+    }
+
+    fn angular(code: u16) -> Option<Unit> {
+        match code {
+            9101 => Some(Unit::new("radian", 1.0)),
+            9102 | 9122 => Some(Unit::degree()),
+            9105 => Some(Unit::new("grad", std::f64::consts::PI / 200.0)),
+            _ => None,
+        }
+    }
+}
+
+/// A reference ellipsoid, where an inverse flattening of 0 denotes a sphere.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ellipsoid {
+    pub name: String,
+    pub semi_major_axis: f64,
+    pub inverse_flattening: f64,
+}
+
+impl Ellipsoid {
+    fn new(name: &str, semi_major_axis: f64, inverse_flattening: f64) -> Ellipsoid {
+        Ellipsoid {
+            name: name.to_string(),
+            semi_major_axis,
+            inverse_flattening,
+        }
+    }
+
+    fn from_epsg(code: u16) -> Option<Ellipsoid> {
+        match code {
+            7004 => Some(Ellipsoid::new("Bessel 1841", 6377397.155, 299.1528128)),
+            7008 => Some(Ellipsoid::new("Clarke 1866", 6378206.4, 294.978698213898)),
+            7019 => Some(Ellipsoid::new("GRS 1980", 6378137.0, 298.257222101)),
+            7022 => Some(Ellipsoid::new("International 1924", 6378388.0, 297.0)),
+            7030 => Some(Ellipsoid::new("WGS 84", 6378137.0, 298.257223563)),
+            _ => None,
+        }
+    }
+
+    pub fn flattening(&self) -> f64 {
+        if self.inverse_flattening == 0.0 {
+            0.0
+        } else {
+            1.0 / self.inverse_flattening
+        }
+    }
+
+    /// The square of the (first) eccentricity.
+    pub fn eccentricity_squared(&self) -> f64 {
+        let f = self.flattening();
+        f * (2.0 - f)
+    }
+}
+
+/// A geodetic datum, i.e., an ellipsoid and how it is positioned. `to_wgs84` holds the 3 or 7
+/// parameters of a Helmert transformation to WGS 84, if known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeodeticDatum {
+    pub name: String,
+    pub ellipsoid: Ellipsoid,
+    pub to_wgs84: Option<Vec<f64>>,
+}
+
+impl GeodeticDatum {
+    fn from_epsg(code: u16) -> Option<GeodeticDatum> {
+        let (name, ellipsoid, to_wgs84) = match code {
+            6149 => ("CH1903", 7004, Some(vec![674.374, 15.056, 405.346])),
+            6150 => ("CH1903+", 7004, Some(vec![674.374, 15.056, 405.346])),
+            6230 => ("European Datum 1950", 7022, None),
+            6258 => (
+                "European Terrestrial Reference System 1989",
+                7019,
+                Some(vec![0.0; 3]),
+            ),
+            6267 => ("North American Datum 1927", 7008, None),
+            6269 => ("North American Datum 1983", 7019, Some(vec![0.0; 3])),
+            6326 => ("World Geodetic System 1984", 7030, Some(vec![0.0; 3])),
+            _ => return None,
+        };
+        Some(GeodeticDatum {
+            name: name.to_string(),
+            ellipsoid: Ellipsoid::from_epsg(ellipsoid)?,
+            to_wgs84,
+        })
+    }
+}
+
+/// A prime meridian, with its longitude (in degrees) east of Greenwich.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrimeMeridian {
+    pub name: String,
+    pub longitude: f64,
+}
+
+impl PrimeMeridian {
+    pub fn greenwich() -> PrimeMeridian {
+        PrimeMeridian {
+            name: "Greenwich".to_string(),
+            longitude: 0.0,
+        }
+    }
+
+    fn from_epsg(code: u16) -> Option<PrimeMeridian> {
+        match code {
+            8901 => Some(PrimeMeridian::greenwich()),
+            8903 => Some(PrimeMeridian {
+                name: "Paris".to_string(),
+                longitude: 2.33722917,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// A geographic CRS, with coordinates given as latitude and longitude.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeographicCrs {
+    pub name: String,
+    pub epsg: Option<u16>,
+    pub datum: GeodeticDatum,
+    pub prime_meridian: PrimeMeridian,
+    pub angular_unit: Unit,
+}
+
+impl GeographicCrs {
+    fn from_epsg(code: u16) -> Option<GeographicCrs> {
+        let (name, datum) = match code {
+            4149 => ("CH1903", 6149),
+            4150 => ("CH1903+", 6150),
+            4230 => ("ED50", 6230),
+            4258 => ("ETRS89", 6258),
+            4267 => ("NAD27", 6267),
+            4269 => ("NAD83", 6269),
+            4326 => ("WGS 84", 6326),
+            _ => return None,
+        };
+        Some(GeographicCrs {
+            name: name.to_string(),
+            epsg: Some(code),
+            datum: GeodeticDatum::from_epsg(datum)?,
+            prime_meridian: PrimeMeridian::greenwich(),
+            angular_unit: Unit::degree(),
+        })
+    }
+}
+
+/// A map projection, with all angles given in degrees and all distances in the linear unit of
+/// the projected CRS.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Projection {
+    /// Transverse Mercator (EPSG method 9807), as used by UTM.
+    TransverseMercator {
+        latitude_of_origin: f64,
+        longitude_of_origin: f64,
+        scale_factor: f64,
+        false_easting: f64,
+        false_northing: f64,
+    },
+    /// Mercator (variant A, EPSG method 9804).
+    Mercator {
+        latitude_of_origin: f64,
+        longitude_of_origin: f64,
+        scale_factor: f64,
+        false_easting: f64,
+        false_northing: f64,
+    },
+    /// The spherical Mercator of web maps (EPSG method 1024).
+    WebMercator {
+        latitude_of_origin: f64,
+        longitude_of_origin: f64,
+        false_easting: f64,
+        false_northing: f64,
+    },
+    /// Lambert Conic Conformal with one standard parallel (EPSG method 9801).
+    LambertConformalConic1SP {
+        latitude_of_origin: f64,
+        longitude_of_origin: f64,
+        scale_factor: f64,
+        false_easting: f64,
+        false_northing: f64,
+    },
+    /// Lambert Conic Conformal with two standard parallels (EPSG method 9802).
+    LambertConformalConic2SP {
+        latitude_of_false_origin: f64,
+        longitude_of_false_origin: f64,
+        standard_parallel_1: f64,
+        standard_parallel_2: f64,
+        false_easting: f64,
+        false_northing: f64,
+    },
+    /// Albers Equal Area (EPSG method 9822).
+    AlbersEqualArea {
+        latitude_of_false_origin: f64,
+        longitude_of_false_origin: f64,
+        standard_parallel_1: f64,
+        standard_parallel_2: f64,
+        false_easting: f64,
+        false_northing: f64,
+    },
+    /// Hotine Oblique Mercator with coordinates relative to the natural origin, where the
+    /// initial line meets the equator of the aposphere (variant A, EPSG method 9812).
+    ObliqueMercatorA {
+        latitude_of_centre: f64,
+        longitude_of_centre: f64,
+        azimuth: f64,
+        rectified_grid_angle: f64,
+        scale_factor: f64,
+        false_easting: f64,
+        false_northing: f64,
+    },
+    /// Hotine Oblique Mercator with coordinates relative to the projection centre (variant B,
+    /// EPSG method 9815), as used by the Swiss LV03 and LV95 grids.
+    ObliqueMercator {
+        latitude_of_centre: f64,
+        longitude_of_centre: f64,
+        azimuth: f64,
+        rectified_grid_angle: f64,
+        scale_factor: f64,
+        false_easting: f64,
+        false_northing: f64,
+    },
+}
+
+impl Projection {
+    fn transverse_mercator_utm(zone: u16, south: bool) -> Projection {
+        Projection::TransverseMercator {
+            latitude_of_origin: 0.0,
+            longitude_of_origin: zone as f64 * 6.0 - 183.0,
+            scale_factor: 0.9996,
+            false_easting: 500000.0,
+            false_northing: if south { 10000000.0 } else { 0.0 },
+        }
+    }
+
+    fn swiss_oblique_mercator(false_easting: f64, false_northing: f64) -> Projection {
+        Projection::ObliqueMercator {
+            latitude_of_centre: 46.9524055555556,
+            longitude_of_centre: 7.43958333333333,
+            azimuth: 90.0,
+            rectified_grid_angle: 90.0,
+            scale_factor: 1.0,
+            false_easting,
+            false_northing,
+        }
+    }
+
+    /// The projection of an EPSG conversion code, as given by the ProjectionGeoKey. Only the
+    /// UTM zones are known.
+    fn from_epsg(code: u16) -> Option<Projection> {
+        match code {
+            16001..=16060 => Some(Projection::transverse_mercator_utm(code - 16000, false)),
+            16101..=16160 => Some(Projection::transverse_mercator_utm(code - 16100, true)),
+            _ => None,
+        }
+    }
+
+    /// The EPSG name and code of the method, and its parameters with their EPSG names and codes.
+    /// Angles are marked by `true`.
+    fn method(&self) -> (&'static str, u16, Vec<Parameter>) {
+        match *self {
+            Projection::TransverseMercator {
+                latitude_of_origin,
+                longitude_of_origin,
+                scale_factor,
+                false_easting,
+                false_northing,
+            } => (
+                "Transverse Mercator",
+                9807,
+                natural_origin_parameters(
+                    latitude_of_origin,
+                    longitude_of_origin,
+                    Some(scale_factor),
+                    false_easting,
+                    false_northing,
+                ),
+            ),
+            Projection::Mercator {
+                latitude_of_origin,
+                longitude_of_origin,
+                scale_factor,
+                false_easting,
+                false_northing,
+            } => (
+                "Mercator (variant A)",
+                9804,
+                natural_origin_parameters(
+                    latitude_of_origin,
+                    longitude_of_origin,
+                    Some(scale_factor),
+                    false_easting,
+                    false_northing,
+                ),
+            ),
+            Projection::WebMercator {
+                latitude_of_origin,
+                longitude_of_origin,
+                false_easting,
+                false_northing,
+            } => (
+                "Popular Visualisation Pseudo Mercator",
+                1024,
+                natural_origin_parameters(
+                    latitude_of_origin,
+                    longitude_of_origin,
+                    None,
+                    false_easting,
+                    false_northing,
+                ),
+            ),
+            Projection::LambertConformalConic1SP {
+                latitude_of_origin,
+                longitude_of_origin,
+                scale_factor,
+                false_easting,
+                false_northing,
+            } => (
+                "Lambert Conic Conformal (1SP)",
+                9801,
+                natural_origin_parameters(
+                    latitude_of_origin,
+                    longitude_of_origin,
+                    Some(scale_factor),
+                    false_easting,
+                    false_northing,
+                ),
+            ),
+            Projection::LambertConformalConic2SP {
+                latitude_of_false_origin,
+                longitude_of_false_origin,
+                standard_parallel_1,
+                standard_parallel_2,
+                false_easting,
+                false_northing,
+            } => (
+                "Lambert Conic Conformal (2SP)",
+                9802,
+                false_origin_parameters(
+                    latitude_of_false_origin,
+                    longitude_of_false_origin,
+                    standard_parallel_1,
+                    standard_parallel_2,
+                    false_easting,
+                    false_northing,
+                ),
+            ),
+            Projection::AlbersEqualArea {
+                latitude_of_false_origin,
+                longitude_of_false_origin,
+                standard_parallel_1,
+                standard_parallel_2,
+                false_easting,
+                false_northing,
+            } => (
+                "Albers Equal Area",
+                9822,
+                false_origin_parameters(
+                    latitude_of_false_origin,
+                    longitude_of_false_origin,
+                    standard_parallel_1,
+                    standard_parallel_2,
+                    false_easting,
+                    false_northing,
+                ),
+            ),
+            Projection::ObliqueMercatorA {
+                latitude_of_centre,
+                longitude_of_centre,
+                azimuth,
+                rectified_grid_angle,
+                scale_factor,
+                false_easting,
+                false_northing,
+            } => (
+                "Hotine Oblique Mercator (variant A)",
+                9812,
+                oblique_mercator_parameters(
+                    latitude_of_centre,
+                    longitude_of_centre,
+                    azimuth,
+                    rectified_grid_angle,
+                    scale_factor,
+                    ("False easting", 8806, false_easting, false),
+                    ("False northing", 8807, false_northing, false),
+                ),
+            ),
+            Projection::ObliqueMercator {
+                latitude_of_centre,
+                longitude_of_centre,
+                azimuth,
+                rectified_grid_angle,
+                scale_factor,
+                false_easting,
+                false_northing,
+            } => (
+                "Hotine Oblique Mercator (variant B)",
+                9815,
+                oblique_mercator_parameters(
+                    latitude_of_centre,
+                    longitude_of_centre,
+                    azimuth,
+                    rectified_grid_angle,
+                    scale_factor,
+                    ("Easting at projection centre", 8816, false_easting, false),
+                    ("Northing at projection centre", 8817, false_northing, false),
+                ),
+            ),
+        }
+    }
+}
+
+/// A projection parameter: its EPSG name and code, its value, and whether it is an angle.
+type Parameter = (&'static str, u16, f64, bool);
+
+fn natural_origin_parameters(
+    latitude: f64,
+    longitude: f64,
+    scale_factor: Option<f64>,
+    false_easting: f64,
+    false_northing: f64,
+) -> Vec<Parameter> {
+    let mut parameters = vec![
+        ("Latitude of natural origin", 8801, latitude, true),
+        ("Longitude of natural origin", 8802, longitude, true),
+    ];
+    if let Some(scale_factor) = scale_factor {
+        parameters.push(("Scale factor at natural origin", 8805, scale_factor, false));
+    }
+    parameters.push(("False easting", 8806, false_easting, false));
+    parameters.push(("False northing", 8807, false_northing, false));
+    parameters
+}
+
+fn false_origin_parameters(
+    latitude: f64,
+    longitude: f64,
+    standard_parallel_1: f64,
+    standard_parallel_2: f64,
+    false_easting: f64,
+    false_northing: f64,
+) -> Vec<Parameter> {
+    vec![
+        ("Latitude of false origin", 8821, latitude, true),
+        ("Longitude of false origin", 8822, longitude, true),
+        (
+            "Latitude of 1st standard parallel",
+            8823,
+            standard_parallel_1,
+            true,
+        ),
+        (
+            "Latitude of 2nd standard parallel",
+            8824,
+            standard_parallel_2,
+            true,
+        ),
+        ("Easting at false origin", 8826, false_easting, false),
+        ("Northing at false origin", 8827, false_northing, false),
+    ]
+}
+
+fn oblique_mercator_parameters(
+    latitude_of_centre: f64,
+    longitude_of_centre: f64,
+    azimuth: f64,
+    rectified_grid_angle: f64,
+    scale_factor: f64,
+    easting: Parameter,
+    northing: Parameter,
+) -> Vec<Parameter> {
+    vec![
+        (
+            "Latitude of projection centre",
+            8811,
+            latitude_of_centre,
+            true,
+        ),
+        (
+            "Longitude of projection centre",
+            8812,
+            longitude_of_centre,
+            true,
+        ),
+        ("Azimuth of initial line", 8813, azimuth, true),
+        (
+            "Angle from Rectified to Skew Grid",
+            8814,
+            rectified_grid_angle,
+            true,
+        ),
+        ("Scale factor on initial line", 8815, scale_factor, false),
+        easting,
+        northing,
+    ]
+}
+
+/// A projected CRS, i.e., a geographic CRS together with a map projection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectedCrs {
+    pub name: String,
+    pub epsg: Option<u16>,
+    pub base: GeographicCrs,
+    pub projection: Projection,
+    pub linear_unit: Unit,
+}
+
+impl ProjectedCrs {
+    fn from_epsg(code: u16) -> Option<ProjectedCrs> {
+        let (name, base, projection) = match code {
+            2056 => (
+                "CH1903+ / LV95".to_string(),
+                4150,
+                Projection::swiss_oblique_mercator(2600000.0, 1200000.0),
+            ),
+            21781 => (
+                "CH1903 / LV03".to_string(),
+                4149,
+                Projection::swiss_oblique_mercator(600000.0, 200000.0),
+            ),
+            3034 => (
+                "ETRS89-extended / LCC Europe".to_string(),
+                4258,
+                Projection::LambertConformalConic2SP {
+                    latitude_of_false_origin: 52.0,
+                    longitude_of_false_origin: 10.0,
+                    standard_parallel_1: 35.0,
+                    standard_parallel_2: 65.0,
+                    false_easting: 4000000.0,
+                    false_northing: 2800000.0,
+                },
+            ),
+            3395 => (
+                "WGS 84 / World Mercator".to_string(),
+                4326,
+                Projection::Mercator {
+                    latitude_of_origin: 0.0,
+                    longitude_of_origin: 0.0,
+                    scale_factor: 1.0,
+                    false_easting: 0.0,
+                    false_northing: 0.0,
+                },
+            ),
+            3857 => (
+                "WGS 84 / Pseudo-Mercator".to_string(),
+                4326,
+                Projection::WebMercator {
+                    latitude_of_origin: 0.0,
+                    longitude_of_origin: 0.0,
+                    false_easting: 0.0,
+                    false_northing: 0.0,
+                },
+            ),
+            5070 => (
+                "NAD83 / Conus Albers".to_string(),
+                4269,
+                Projection::AlbersEqualArea {
+                    latitude_of_false_origin: 23.0,
+                    longitude_of_false_origin: -96.0,
+                    standard_parallel_1: 29.5,
+                    standard_parallel_2: 45.5,
+                    false_easting: 0.0,
+                    false_northing: 0.0,
+                },
+            ),
+            25828..=25838 => utm("ETRS89", 4258, code - 25800, false),
+            26901..=26923 => utm("NAD83", 4269, code - 26900, false),
+            32601..=32660 => utm("WGS 84", 4326, code - 32600, false),
+            32701..=32760 => utm("WGS 84", 4326, code - 32700, true),
+            _ => return None,
+        };
+        Some(ProjectedCrs {
+            name,
+            epsg: Some(code),
+            base: GeographicCrs::from_epsg(base)?,
+            projection,
+            linear_unit: Unit::metre(),
+        })
+    }
+}
+
+fn utm(base_name: &str, base: u16, zone: u16, south: bool) -> (String, u16, Projection) {
+    let hemisphere = if south { "S" } else { "N" };
+    (
+        format!("{} / UTM zone {}{}", base_name, zone, hemisphere),
+        base,
+        Projection::transverse_mercator_utm(zone, south),
+    )
+}
+
+/// The coordinate reference system of an image's model space.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Crs {
+    Geographic(GeographicCrs),
+    Projected(ProjectedCrs),
+    /// A CRS given by an EPSG code whose definition is not known to this crate.
+    Epsg(u16),
+}
+
+/// Finds the value of the first GeoKey of a variant.
+macro_rules! find_key {
+    ($keys:expr, $variant:path) => {
+        $keys.iter().find_map(|key| match *key {
+            $variant(ref value) => Some(value.clone()),
+            _ => None,
+        })
+    };
+}
+
+impl Crs {
+    /// Resolves the CRS described by the GeoKeys.
+    ///
+    /// Geographic and projected CRSs are either given by an EPSG code or defined by further
+    /// keys (user-defined). Only a small set of common EPSG codes is known, others are returned
+    /// as `Crs::Epsg`. Geocentric CRSs are not supported.
+    pub fn from_geo_keys(keys: &[GeoKey]) -> Result<Crs> {
+        let projected = find_key!(keys, GeoKey::ProjectedCSTypeGeoKey);
+        let geographic = find_key!(keys, GeoKey::GeographicTypeGeoKey);
+        let model_type = match find_key!(keys, GeoKey::GTModelTypeGeoKey) {
+            Some(model_type) => model_type,
+            // Fall back to whatever CRS keys are present.
+            None if projected.is_some() => 1,
+            None if geographic.is_some() => 2,
+            None => return Err(missing_key("GTModelTypeGeoKey")),
+        };
+        match model_type {
+            1 => match projected {
+                Some(USER_DEFINED) => Ok(Crs::Projected(user_defined_projected_crs(keys)?)),
+                Some(code) => Ok(ProjectedCrs::from_epsg(code)
+                    .map(Crs::Projected)
+                    .unwrap_or(Crs::Epsg(code))),
+                None => Err(missing_key("ProjectedCSTypeGeoKey")),
+            },
+            2 => match geographic {
+                Some(USER_DEFINED) => Ok(Crs::Geographic(user_defined_geographic_crs(keys)?)),
+                Some(code) => Ok(GeographicCrs::from_epsg(code)
+                    .map(Crs::Geographic)
+                    .unwrap_or(Crs::Epsg(code))),
+                None => Err(missing_key("GeographicTypeGeoKey")),
+            },
+            3 => Err(GeoTiffError::UnsupportedCrs(
+                "Geocentric CRSs are not supported".to_string(),
+            )),
+            model_type => Err(GeoTiffError::UnsupportedCrs(format!(
+                "Unknown model type {}",
+                model_type
+            ))),
+        }
+    }
+
+    /// The EPSG code of the CRS, or `None` if it is user-defined.
+    pub fn epsg_code(&self) -> Option<u16> {
+        match *self {
+            Crs::Geographic(ref crs) => crs.epsg,
+            Crs::Projected(ref crs) => crs.epsg,
+            Crs::Epsg(code) => Some(code),
+        }
+    }
+
+    /// The CRS as OGC WKT2 (ISO 19162:2019), or `None` if the CRS is only known by its EPSG
+    /// code.
+    pub fn to_wkt(&self) -> Option<String> {
+        let mut wkt = String::new();
+        match *self {
+            Crs::Geographic(ref crs) => write_geographic_crs(&mut wkt, "GEOGCRS", crs, true),
+            Crs::Projected(ref crs) => write_projected_crs(&mut wkt, crs),
+            Crs::Epsg(_) => return None,
+        }
+        Some(wkt)
+    }
+}
+
+fn missing_key(name: &str) -> GeoTiffError {
+    GeoTiffError::InvalidTagValue(TIFFTag::GeoKeyDirectoryTag, format!("Missing {}", name))
+}
+
+fn unknown_code(what: &str, code: u16) -> GeoTiffError {
+    GeoTiffError::UnsupportedCrs(format!("Unknown {} code {}", what, code))
+}
+
+/// Builds a user-defined geographic CRS, where each of its parts may in turn be given by an
+/// EPSG code or be user-defined.
+fn user_defined_geographic_crs(keys: &[GeoKey]) -> Result<GeographicCrs> {
+    let name = find_key!(keys, GeoKey::GeogCitationGeoKey).unwrap_or("unknown".to_string());
+    let angular_unit = match find_key!(keys, GeoKey::GeogAngularUnitsGeoKey) {
+        None => Unit::degree(),
+        Some(USER_DEFINED) => Unit::new(
+            "unknown",
+            find_key!(keys, GeoKey::GeogAngularUnitSizeGeoKey)
+                .ok_or(missing_key("GeogAngularUnitSizeGeoKey"))?,
+        ),
+        Some(code) => Unit::angular(code).ok_or(unknown_code("angular unit", code))?,
+    };
+    let datum = match find_key!(keys, GeoKey::GeogGeodeticDatumGeoKey) {
+        None | Some(USER_DEFINED) => {
+            let ellipsoid = match find_key!(keys, GeoKey::GeogEllipsoidGeoKey) {
+                None | Some(USER_DEFINED) => user_defined_ellipsoid(keys)?,
+                Some(code) => Ellipsoid::from_epsg(code).ok_or(unknown_code("ellipsoid", code))?,
+            };
+            GeodeticDatum {
+                name: name.clone(),
+                ellipsoid,
+                to_wgs84: None,
+            }
+        }
+        Some(code) => GeodeticDatum::from_epsg(code).ok_or(unknown_code("datum", code))?,
+    };
+    let datum = match find_key!(keys, GeoKey::GeogTOWGS84GeoKey) {
+        Some(to_wgs84) => GeodeticDatum {
+            to_wgs84: Some(to_wgs84),
+            ..datum
+        },
+        None => datum,
+    };
+    let prime_meridian = match find_key!(keys, GeoKey::GeogPrimeMeridianGeoKey) {
+        None | Some(USER_DEFINED) => PrimeMeridian {
+            name: "unknown".to_string(),
+            longitude: find_key!(keys, GeoKey::GeogPrimeMeridianLongGeoKey)
+                .map(|longitude| to_degrees(longitude, &angular_unit))
+                .unwrap_or(0.0),
+        },
+        Some(code) => PrimeMeridian::from_epsg(code).ok_or(unknown_code("prime meridian", code))?,
+    };
+    Ok(GeographicCrs {
+        name,
+        epsg: None,
+        datum,
+        prime_meridian,
+        angular_unit,
+    })
+}
+
+/// Builds a user-defined ellipsoid from its semi-major axis and either its inverse flattening
+/// or its semi-minor axis.
+fn user_defined_ellipsoid(keys: &[GeoKey]) -> Result<Ellipsoid> {
+    let semi_major_axis = find_key!(keys, GeoKey::GeogSemiMajorAxisGeoKey)
+        .ok_or(missing_key("GeogSemiMajorAxisGeoKey"))?;
+    let inverse_flattening = match find_key!(keys, GeoKey::GeogInvFlatteningGeoKey) {
+        Some(inverse_flattening) => inverse_flattening,
+        None => {
+            let semi_minor_axis = find_key!(keys, GeoKey::GeogSemiMinorAxisGeoKey)
+                .ok_or(missing_key("GeogInvFlatteningGeoKey"))?;
+            if semi_minor_axis == semi_major_axis {
+                0.0
+            } else {
+                semi_major_axis / (semi_major_axis - semi_minor_axis)
+            }
+        }
+    };
+    Ok(Ellipsoid::new(
+        "unknown",
+        semi_major_axis,
+        inverse_flattening,
+    ))
+}
+
+/// Builds a user-defined projected CRS from its geographic CRS and its projection, which is
+/// either given by an EPSG conversion code or by a coordinate transformation and its
+/// parameters.
+fn user_defined_projected_crs(keys: &[GeoKey]) -> Result<ProjectedCrs> {
+    let name = find_key!(keys, GeoKey::PCSCitationGeoKey)
+        .or(find_key!(keys, GeoKey::GTCitationGeoKey))
+        .unwrap_or("unknown".to_string());
+    let base = match find_key!(keys, GeoKey::GeographicTypeGeoKey) {
+        None | Some(USER_DEFINED) => user_defined_geographic_crs(keys)?,
+        Some(code) => GeographicCrs::from_epsg(code).ok_or(unknown_code("geographic CRS", code))?,
+    };
+    let linear_unit = match find_key!(keys, GeoKey::ProjLinearUnitsGeoKey) {
+        None => Unit::metre(),
+        Some(USER_DEFINED) => Unit::new(
+            "unknown",
+            find_key!(keys, GeoKey::ProjLinearUnitSizeGeoKey)
+                .ok_or(missing_key("ProjLinearUnitSizeGeoKey"))?,
+        ),
+        Some(code) => Unit::linear(code).ok_or(unknown_code("linear unit", code))?,
+    };
+    let projection = match find_key!(keys, GeoKey::ProjectionGeoKey) {
+        None | Some(USER_DEFINED) => user_defined_projection(keys, &base)?,
+        Some(code) => Projection::from_epsg(code).ok_or(unknown_code("projection", code))?,
+    };
+    Ok(ProjectedCrs {
+        name,
+        epsg: None,
+        base,
+        projection,
+        linear_unit,
+    })
+}
+
+/// Converts an angle given in `unit` into degrees.
+fn to_degrees(angle: f64, unit: &Unit) -> f64 {
+    (angle * unit.factor).to_degrees()
+}
+
+/// Builds a projection from the ProjCoordTransGeoKey and the projection parameter keys.
+///
+/// Writers don't agree on which keys to use for conic projections, so the parameters of the
+/// false origin fall back to the ones of the natural origin.
+fn user_defined_projection(keys: &[GeoKey], base: &GeographicCrs) -> Result<Projection> {
+    let angle = |value: Option<f64>| to_degrees(value.unwrap_or(0.0), &base.angular_unit);
+    let natural_origin_latitude = angle(find_key!(keys, GeoKey::ProjNatOriginLatGeoKey));
+    let natural_origin_longitude = angle(find_key!(keys, GeoKey::ProjNatOriginLongGeoKey));
+    let false_easting = find_key!(keys, GeoKey::ProjFalseEastingGeoKey).unwrap_or(0.0);
+    let false_northing = find_key!(keys, GeoKey::ProjFalseNorthingGeoKey).unwrap_or(0.0);
+    let scale_factor = find_key!(keys, GeoKey::ProjScaleAtNatOriginGeoKey).unwrap_or(1.0);
+    let standard_parallel_1 = angle(find_key!(keys, GeoKey::ProjStdParallel1GeoKey));
+    let standard_parallel_2 = angle(find_key!(keys, GeoKey::ProjStdParallel2GeoKey));
+    let false_origin_latitude = find_key!(keys, GeoKey::ProjFalseOriginLatGeoKey)
+        .map(|value| angle(Some(value)))
+        .unwrap_or(natural_origin_latitude);
+    let false_origin_longitude = find_key!(keys, GeoKey::ProjFalseOriginLongGeoKey)
+        .map(|value| angle(Some(value)))
+        .unwrap_or(natural_origin_longitude);
+    let false_origin_easting =
+        find_key!(keys, GeoKey::ProjFalseOriginEastingGeoKey).unwrap_or(false_easting);
+    let false_origin_northing =
+        find_key!(keys, GeoKey::ProjFalseOriginNorthingGeoKey).unwrap_or(false_northing);
+
+    match find_key!(keys, GeoKey::ProjCoordTransGeoKey) {
+        Some(1) => Ok(Projection::TransverseMercator {
+            latitude_of_origin: natural_origin_latitude,
+            longitude_of_origin: natural_origin_longitude,
+            scale_factor,
+            false_easting,
+            false_northing,
+        }),
+        // CT_ObliqueMercator is variant A, as in libgeotiff and GDAL, which write variant B by
+        // its EPSG method code instead.
+        Some(method @ (3 | 9815)) => {
+            let latitude_of_centre = angle(find_key!(keys, GeoKey::ProjCenterLatGeoKey));
+            let longitude_of_centre = angle(find_key!(keys, GeoKey::ProjCenterLongGeoKey));
+            let azimuth = angle(find_key!(keys, GeoKey::ProjAzimuthAngleGeoKey));
+            let rectified_grid_angle = find_key!(keys, GeoKey::ProjRectifiedGridAngleGeoKey)
+                .map(|value| angle(Some(value)))
+                .unwrap_or(azimuth);
+            let scale_factor = find_key!(keys, GeoKey::ProjScaleAtCenterGeoKey).unwrap_or(1.0);
+            let center_easting = find_key!(keys, GeoKey::ProjCenterEastingGeoKey);
+            let center_northing = find_key!(keys, GeoKey::ProjCenterNorthingGeoKey);
+            Ok(if method == 3 {
+                Projection::ObliqueMercatorA {
+                    latitude_of_centre,
+                    longitude_of_centre,
+                    azimuth,
+                    rectified_grid_angle,
+                    scale_factor,
+                    false_easting: find_key!(keys, GeoKey::ProjFalseEastingGeoKey)
+                        .or(center_easting)
+                        .unwrap_or(0.0),
+                    false_northing: find_key!(keys, GeoKey::ProjFalseNorthingGeoKey)
+                        .or(center_northing)
+                        .unwrap_or(0.0),
+                }
+            } else {
+                Projection::ObliqueMercator {
+                    latitude_of_centre,
+                    longitude_of_centre,
+                    azimuth,
+                    rectified_grid_angle,
+                    scale_factor,
+                    false_easting: center_easting.unwrap_or(false_easting),
+                    false_northing: center_northing.unwrap_or(false_northing),
+                }
+            })
+        }
+        Some(7) => {
+            // Mercator (2SP) is given by a standard parallel instead of the scale factor, which
+            // is the scale along that parallel.
+            let scale_factor = match (
+                find_key!(keys, GeoKey::ProjScaleAtNatOriginGeoKey),
+                find_key!(keys, GeoKey::ProjStdParallel1GeoKey),
+            ) {
+                (None, Some(_)) => {
+                    let e2 = base.datum.ellipsoid.eccentricity_squared();
+                    let latitude = standard_parallel_1.to_radians();
+                    latitude.cos() / (1.0 - e2 * latitude.sin().powi(2)).sqrt()
+                }
+                _ => scale_factor,
+            };
+            Ok(Projection::Mercator {
+                latitude_of_origin: natural_origin_latitude,
+                longitude_of_origin: natural_origin_longitude,
+                scale_factor,
+                false_easting,
+                false_northing,
+            })
+        }
+        Some(8) => Ok(Projection::LambertConformalConic2SP {
+            latitude_of_false_origin: false_origin_latitude,
+            longitude_of_false_origin: false_origin_longitude,
+            standard_parallel_1,
+            standard_parallel_2,
+            false_easting: false_origin_easting,
+            false_northing: false_origin_northing,
+        }),
+        Some(9) => Ok(Projection::LambertConformalConic1SP {
+            latitude_of_origin: natural_origin_latitude,
+            longitude_of_origin: natural_origin_longitude,
+            scale_factor,
+            false_easting,
+            false_northing,
+        }),
+        Some(11) => Ok(Projection::AlbersEqualArea {
+            latitude_of_false_origin: false_origin_latitude,
+            longitude_of_false_origin: false_origin_longitude,
+            standard_parallel_1,
+            standard_parallel_2,
+            false_easting: false_origin_easting,
+            false_northing: false_origin_northing,
+        }),
+        Some(method) => Err(GeoTiffError::UnsupportedCrs(format!(
+            "Unsupported coordinate transformation {}",
+            method
+        ))),
+        None => Err(missing_key("ProjCoordTransGeoKey")),
+    }
+}
+
+fn write_id(wkt: &mut String, epsg: Option<u16>) {
+    if let Some(code) = epsg {
+        write!(wkt, ",ID[\"EPSG\",{}]", code).unwrap();
+    }
+}
+
+fn write_unit(wkt: &mut String, keyword: &str, unit: &Unit) {
+    write!(wkt, "{}[\"{}\",{}]", keyword, unit.name, unit.factor).unwrap();
+}
+
+/// Writes a geographic CRS. As the base of a projected CRS, it has no coordinate system.
+fn write_geographic_crs(wkt: &mut String, keyword: &str, crs: &GeographicCrs, with_cs: bool) {
+    let ellipsoid = &crs.datum.ellipsoid;
+    write!(
+        wkt,
+        "{}[\"{}\",DATUM[\"{}\",ELLIPSOID[\"{}\",{},{},LENGTHUNIT[\"metre\",1]]],\
+         PRIMEM[\"{}\",{},ANGLEUNIT[\"degree\",{}]]",
+        keyword,
+        crs.name,
+        crs.datum.name,
+        ellipsoid.name,
+        ellipsoid.semi_major_axis,
+        ellipsoid.inverse_flattening,
+        crs.prime_meridian.name,
+        crs.prime_meridian.longitude,
+        Unit::degree().factor
+    )
+    .unwrap();
+    if with_cs {
+        wkt.push_str(",CS[ellipsoidal,2],AXIS[\"geodetic latitude (Lat)\",north,ORDER[1],");
+        write_unit(wkt, "ANGLEUNIT", &crs.angular_unit);
+        wkt.push_str("],AXIS[\"geodetic longitude (Lon)\",east,ORDER[2],");
+        write_unit(wkt, "ANGLEUNIT", &crs.angular_unit);
+        wkt.push(']');
+    }
+    write_id(wkt, crs.epsg);
+    wkt.push(']');
+}
+
+fn write_projected_crs(wkt: &mut String, crs: &ProjectedCrs) {
+    write!(wkt, "PROJCRS[\"{}\",", crs.name).unwrap();
+    write_geographic_crs(wkt, "BASEGEOGCRS", &crs.base, false);
+    let (method, method_code, parameters) = crs.projection.method();
+    write!(
+        wkt,
+        ",CONVERSION[\"unknown\",METHOD[\"{}\",ID[\"EPSG\",{}]]",
+        method, method_code
+    )
+    .unwrap();
+    for (name, code, value, is_angle) in parameters {
+        write!(wkt, ",PARAMETER[\"{}\",{},", name, value).unwrap();
+        if is_angle {
+            write_unit(wkt, "ANGLEUNIT", &Unit::degree());
+        } else if code == 8805 || code == 8815 {
+            wkt.push_str("SCALEUNIT[\"unity\",1]");
+        } else {
+            write_unit(wkt, "LENGTHUNIT", &crs.linear_unit);
+        }
+        write!(wkt, ",ID[\"EPSG\",{}]]", code).unwrap();
+    }
+    wkt.push_str("],CS[Cartesian,2],AXIS[\"easting (E)\",east,ORDER[1],");
+    write_unit(wkt, "LENGTHUNIT", &crs.linear_unit);
+    wkt.push_str("],AXIS[\"northing (N)\",north,ORDER[2],");
+    write_unit(wkt, "LENGTHUNIT", &crs.linear_unit);
+    wkt.push(']');
+    write_id(wkt, crs.epsg);
+    wkt.push(']');
+}
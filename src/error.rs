@@ -0,0 +1,150 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use lowlevel::{TIFFTag, TagType};
+
+/// The errors that can occur while reading a (Geo)TIFF.
+#[derive(Debug)]
+pub enum GeoTiffError {
+    /// An error of the underlying reader.
+    Io(io::Error),
+    /// The file does not start with a valid TIFF or BigTIFF header.
+    InvalidHeader(String),
+    /// The file ends before all the data referenced by the header, the IFDs, or the strips and
+    /// tiles could be read.
+    TruncatedData,
+    /// The structure of the IFDs is broken, e.g., the IFD chain loops.
+    InvalidIFD(String),
+    /// An IFD entry uses a tag ID that is not known.
+    UnknownTag(u16),
+    /// An IFD entry uses a field type that is not known.
+    UnknownTagType(u16),
+    /// A tag required to read the image is missing.
+    MissingTag(TIFFTag),
+    /// A tag is stored with a field type that can't be interpreted for it.
+    InvalidTagType(TIFFTag, TagType),
+    /// A tag has a value that can't be used, e.g., a tile width of 0.
+    InvalidTagValue(TIFFTag, String),
+    /// The image data is compressed with an unsupported compression scheme.
+    UnsupportedCompression(u16),
+    /// The image data uses an unsupported predictor, or one that is not supported for its
+    /// sample size.
+    UnsupportedPredictor(u16),
+    /// The combination of sample format and bits per sample is not supported.
+    UnsupportedSampleFormat(u16, usize),
+    /// The image data uses an unsupported planar configuration, i.e., its bands are stored in
+    /// separate planes.
+    UnsupportedPlanarConfiguration(u16),
+    /// The GeoKey directory has a version (key directory version, revision, minor revision)
+    /// that is not supported.
+    UnsupportedGeoKeyVersion(u16, u16, u16),
+    /// The image is neither georeferenced by an (invertible) affine transform nor by ground
+    /// control points, so pixel and model coordinates can't be converted into each other.
+    NoGeoTransform,
+    /// The GeoKeys describe a CRS that is not supported, e.g., a geocentric one or one using an
+    /// unknown projection.
+    UnsupportedCrs(String),
+    /// The (compressed) image data is corrupt.
+    CorruptData(String),
+    /// There is no image (IFD) with this index.
+    NoSuchImage(usize),
+    /// A window to read is empty, exceeds the image, or refers to bands that do not exist.
+    InvalidWindow(String),
+}
+
+pub type Result<T> = std::result::Result<T, GeoTiffError>;
+
+impl GeoTiffError {
+    /// Copies an error that is kept, e.g. the one of fitting a transform, to report it again.
+    /// I/O errors keep their kind and message only.
+    pub(crate) fn duplicate(&self) -> GeoTiffError {
+        match *self {
+            GeoTiffError::Io(ref e) => GeoTiffError::Io(io::Error::new(e.kind(), e.to_string())),
+            GeoTiffError::InvalidHeader(ref msg) => GeoTiffError::InvalidHeader(msg.clone()),
+            GeoTiffError::TruncatedData => GeoTiffError::TruncatedData,
+            GeoTiffError::InvalidIFD(ref msg) => GeoTiffError::InvalidIFD(msg.clone()),
+            GeoTiffError::UnknownTag(tag) => GeoTiffError::UnknownTag(tag),
+            GeoTiffError::UnknownTagType(tpe) => GeoTiffError::UnknownTagType(tpe),
+            GeoTiffError::MissingTag(tag) => GeoTiffError::MissingTag(tag),
+            GeoTiffError::InvalidTagType(tag, tpe) => GeoTiffError::InvalidTagType(tag, tpe),
+            GeoTiffError::InvalidTagValue(tag, ref msg) => {
+                GeoTiffError::InvalidTagValue(tag, msg.clone())
+            }
+            GeoTiffError::UnsupportedCompression(c) => GeoTiffError::UnsupportedCompression(c),
+            GeoTiffError::UnsupportedPredictor(p) => GeoTiffError::UnsupportedPredictor(p),
+            GeoTiffError::UnsupportedSampleFormat(format, bits) => {
+                GeoTiffError::UnsupportedSampleFormat(format, bits)
+            }
+            GeoTiffError::UnsupportedPlanarConfiguration(p) => {
+                GeoTiffError::UnsupportedPlanarConfiguration(p)
+            }
+            GeoTiffError::UnsupportedGeoKeyVersion(version, revision, minor_revision) => {
+                GeoTiffError::UnsupportedGeoKeyVersion(version, revision, minor_revision)
+            }
+            GeoTiffError::NoGeoTransform => GeoTiffError::NoGeoTransform,
+            GeoTiffError::UnsupportedCrs(ref msg) => GeoTiffError::UnsupportedCrs(msg.clone()),
+            GeoTiffError::CorruptData(ref msg) => GeoTiffError::CorruptData(msg.clone()),
+            GeoTiffError::NoSuchImage(index) => GeoTiffError::NoSuchImage(index),
+            GeoTiffError::InvalidWindow(ref msg) => GeoTiffError::InvalidWindow(msg.clone()),
+        }
+    }
+}
+
+impl fmt::Display for GeoTiffError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GeoTiffError::Io(ref e) => write!(f, "I/O error: {}", e),
+            GeoTiffError::InvalidHeader(ref msg) => write!(f, "Invalid header: {}", msg),
+            GeoTiffError::TruncatedData => write!(f, "Unexpected end of file"),
+            GeoTiffError::InvalidIFD(ref msg) => write!(f, "Invalid IFD: {}", msg),
+            GeoTiffError::UnknownTag(tag) => write!(f, "Unknown tag {:04X}", tag),
+            GeoTiffError::UnknownTagType(tpe) => write!(f, "Unknown tag type {:04X}", tpe),
+            GeoTiffError::MissingTag(tag) => write!(f, "Missing tag {:?}", tag),
+            GeoTiffError::InvalidTagType(tag, ref tpe) => {
+                write!(f, "Invalid type {:?} for tag {:?}", tpe, tag)
+            }
+            GeoTiffError::InvalidTagValue(tag, ref msg) => {
+                write!(f, "Invalid value for tag {:?}: {}", tag, msg)
+            }
+            GeoTiffError::UnsupportedCompression(c) => write!(f, "Unsupported compression {}", c),
+            GeoTiffError::UnsupportedPredictor(p) => write!(f, "Unsupported predictor {}", p),
+            GeoTiffError::UnsupportedSampleFormat(format, bits) => write!(
+                f,
+                "Unsupported sample format {} with {} bits per sample",
+                format, bits
+            ),
+            GeoTiffError::UnsupportedPlanarConfiguration(p) => {
+                write!(f, "Unsupported planar configuration {}", p)
+            }
+            GeoTiffError::UnsupportedGeoKeyVersion(version, revision, minor_revision) => write!(
+                f,
+                "Unsupported GeoKey directory version {} (revision {}.{})",
+                version, revision, minor_revision
+            ),
+            GeoTiffError::NoGeoTransform => write!(f, "No affine geotransform"),
+            GeoTiffError::UnsupportedCrs(ref msg) => write!(f, "Unsupported CRS: {}", msg),
+            GeoTiffError::CorruptData(ref msg) => write!(f, "Corrupt image data: {}", msg),
+            GeoTiffError::NoSuchImage(index) => write!(f, "No image with index {}", index),
+            GeoTiffError::InvalidWindow(ref msg) => write!(f, "Invalid window: {}", msg),
+        }
+    }
+}
+
+impl Error for GeoTiffError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            GeoTiffError::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GeoTiffError {
+    fn from(err: io::Error) -> GeoTiffError {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => GeoTiffError::TruncatedData,
+            _ => GeoTiffError::Io(err),
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use error::{GeoTiffError, Result};
+use geotransform::RasterType;
+use lowlevel::TIFFTag;
+use tiff::IFD;
+
+/// A ground control point, i.e., a tiepoint linking a raster position to model coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gcp {
+    pub col: f64,
+    pub row: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Gcp {
+    /// Reads all tiepoints of an IFD, or none if there is no ModelTiepoint tag.
+    ///
+    /// Like the geotransform, raster positions of PixelIsPoint images are shifted by half a
+    /// pixel unless `point_geo_ignore` is set, so that they always refer to pixel corners.
+    pub fn from_ifd(ifd: &IFD, point_geo_ignore: bool) -> Result<Vec<Gcp>> {
+        let entry = match ifd.get(TIFFTag::ModelTiepointTag) {
+            Some(entry) => entry,
+            None => return Ok(Vec::new()),
+        };
+        let values = entry
+            .value
+            .as_doubles()
+            .ok_or(GeoTiffError::InvalidTagType(entry.tag, entry.tpe))?;
+        if values.len() % 6 != 0 {
+            return Err(GeoTiffError::InvalidTagValue(
+                TIFFTag::ModelTiepointTag,
+                format!("Expected a multiple of 6 values, found {}", values.len()),
+            ));
+        }
+        let shift = if !point_geo_ignore && ifd.get_raster_type() == RasterType::PixelIsPoint {
+            0.5
+        } else {
+            0.0
+        };
+        Ok(values
+            .chunks_exact(6)
+            .map(|tiepoint| Gcp {
+                col: tiepoint[0] + shift,
+                row: tiepoint[1] + shift,
+                x: tiepoint[3],
+                y: tiepoint[4],
+                z: tiepoint[5],
+            })
+            .collect())
+    }
+}
+
+/// How the transform between raster and model space is fitted to the ground control points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GcpTransformMethod {
+    /// A least squares fitted polynomial of first order (an affine transform), which needs at
+    /// least 3 GCPs.
+    Polynomial1,
+    /// A least squares fitted polynomial of second order, which needs at least 6 GCPs.
+    Polynomial2,
+    /// A least squares fitted polynomial of third order, which needs at least 10 GCPs.
+    Polynomial3,
+    /// A thin plate spline, which exactly matches every GCP and needs at least 3 GCPs that
+    /// are not on a line.
+    ThinPlateSpline,
+}
+
+/// A transform between raster and model space fitted to ground control points.
+///
+/// Fitting is not exactly cheap for many GCPs, so for repeated conversions the transform
+/// should be created once and then reused.
+#[derive(Debug, Clone)]
+pub struct GcpTransform {
+    forward: Fit,
+    inverse: Fit,
+}
+
+impl GcpTransform {
+    /// Fits the transform to the GCPs. Both directions are fitted separately, so for polynomials
+    /// they are not exactly the inverse of each other.
+    pub fn new(gcps: &[Gcp], method: GcpTransformMethod) -> Result<GcpTransform> {
+        let pixels: Vec<_> = gcps.iter().map(|gcp| (gcp.col, gcp.row)).collect();
+        let model: Vec<_> = gcps.iter().map(|gcp| (gcp.x, gcp.y)).collect();
+        Ok(GcpTransform {
+            forward: Fit::new(&pixels, &model, method)?,
+            inverse: Fit::new(&model, &pixels, method)?,
+        })
+    }
+
+    /// Transforms raster coordinates (`col`, `row`) into model coordinates (x, y).
+    pub fn pixel_to_model(&self, col: f64, row: f64) -> (f64, f64) {
+        self.forward.apply(col, row)
+    }
+
+    /// Transforms model coordinates (x, y) into raster coordinates (col, row).
+    pub fn model_to_pixel(&self, x: f64, y: f64) -> (f64, f64) {
+        self.inverse.apply(x, y)
+    }
+}
+
+/// A fitted mapping from source to target points, in either direction.
+#[derive(Debug, Clone)]
+enum Fit {
+    Polynomial {
+        order: usize,
+        normalization: Normalization,
+        coefficients: Vec<[f64; 2]>,
+    },
+    ThinPlateSpline {
+        normalization: Normalization,
+        points: Vec<(f64, f64)>,
+        weights: Vec<[f64; 2]>,
+    },
+}
+
+impl Fit {
+    fn new(
+        source: &[(f64, f64)],
+        target: &[(f64, f64)],
+        method: GcpTransformMethod,
+    ) -> Result<Fit> {
+        let normalization = Normalization::new(source);
+        let points: Vec<_> = source
+            .iter()
+            .map(|&(x, y)| normalization.apply(x, y))
+            .collect();
+        let targets: Vec<_> = target.iter().map(|&(x, y)| [x, y]).collect();
+        let order = match method {
+            GcpTransformMethod::Polynomial1 => 1,
+            GcpTransformMethod::Polynomial2 => 2,
+            GcpTransformMethod::Polynomial3 => 3,
+            GcpTransformMethod::ThinPlateSpline => {
+                let weights = fit_thin_plate_spline(&points, &targets)?;
+                return Ok(Fit::ThinPlateSpline {
+                    normalization,
+                    points,
+                    weights,
+                });
+            }
+        };
+        let coefficients = fit_polynomial(order, &points, &targets)?;
+        Ok(Fit::Polynomial {
+            order,
+            normalization,
+            coefficients,
+        })
+    }
+
+    fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        let (values, coefficients) = match *self {
+            Fit::Polynomial {
+                order,
+                ref normalization,
+                ref coefficients,
+            } => {
+                let (x, y) = normalization.apply(x, y);
+                (polynomial_terms(order, x, y), coefficients)
+            }
+            Fit::ThinPlateSpline {
+                ref normalization,
+                ref points,
+                ref weights,
+            } => {
+                let (x, y) = normalization.apply(x, y);
+                (thin_plate_spline_terms(points, x, y), weights)
+            }
+        };
+        values
+            .iter()
+            .zip(coefficients)
+            .fold((0.0, 0.0), |(x, y), (value, c)| {
+                (x + value * c[0], y + value * c[1])
+            })
+    }
+}
+
+/// Moves points around the origin and scales them to roughly [-1, 1], which keeps the equation
+/// systems well conditioned for large coordinates (e.g., projected coordinates in metres).
+#[derive(Debug, Clone)]
+struct Normalization {
+    offset: (f64, f64),
+    scale: f64,
+}
+
+impl Normalization {
+    fn new(points: &[(f64, f64)]) -> Normalization {
+        let n = points.len().max(1) as f64;
+        let offset = points
+            .iter()
+            .fold((0.0, 0.0), |(x, y), p| (x + p.0 / n, y + p.1 / n));
+        let extent = points.iter().fold(0.0_f64, |extent, p| {
+            extent
+                .max((p.0 - offset.0).abs())
+                .max((p.1 - offset.1).abs())
+        });
+        Normalization {
+            offset,
+            scale: if extent > 0.0 { 1.0 / extent } else { 1.0 },
+        }
+    }
+
+    fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            (x - self.offset.0) * self.scale,
+            (y - self.offset.1) * self.scale,
+        )
+    }
+}
+
+/// All monomials x^i * y^j with i + j <= `order`.
+fn polynomial_terms(order: usize, x: f64, y: f64) -> Vec<f64> {
+    let mut terms = Vec::new();
+    for degree in 0..=order {
+        for j in 0..=degree {
+            terms.push(x.powi((degree - j) as i32) * y.powi(j as i32));
+        }
+    }
+    terms
+}
+
+/// Fits a polynomial by least squares, i.e., solves the normal equations.
+fn fit_polynomial(
+    order: usize,
+    points: &[(f64, f64)],
+    targets: &[[f64; 2]],
+) -> Result<Vec<[f64; 2]>> {
+    let rows: Vec<_> = points
+        .iter()
+        .map(|&(x, y)| polynomial_terms(order, x, y))
+        .collect();
+    let terms = polynomial_terms(order, 0.0, 0.0).len();
+    if points.len() < terms {
+        return Err(invalid_gcps(format!(
+            "A polynomial of order {} needs at least {} GCPs, found {}",
+            order,
+            terms,
+            points.len()
+        )));
+    }
+    let mut matrix = vec![vec![0.0; terms]; terms];
+    let mut rhs = vec![[0.0; 2]; terms];
+    for (row, target) in rows.iter().zip(targets) {
+        for i in 0..terms {
+            for j in 0..terms {
+                matrix[i][j] += row[i] * row[j];
+            }
+            rhs[i][0] += row[i] * target[0];
+            rhs[i][1] += row[i] * target[1];
+        }
+    }
+    solve(matrix, rhs)
+}
+
+/// The radial basis function of thin plate splines, r^2 * ln(r) (up to a constant factor).
+fn thin_plate_spline_kernel(dx: f64, dy: f64) -> f64 {
+    let r2 = dx * dx + dy * dy;
+    if r2 == 0.0 {
+        0.0
+    } else {
+        r2 * r2.ln()
+    }
+}
+
+/// The kernel values for every control point, followed by the affine terms 1, x and y.
+fn thin_plate_spline_terms(points: &[(f64, f64)], x: f64, y: f64) -> Vec<f64> {
+    points
+        .iter()
+        .map(|p| thin_plate_spline_kernel(x - p.0, y - p.1))
+        .chain([1.0, x, y])
+        .collect()
+}
+
+/// Fits a thin plate spline, i.e., solves [K P; P^T 0] [w; a] = [v; 0].
+fn fit_thin_plate_spline(points: &[(f64, f64)], targets: &[[f64; 2]]) -> Result<Vec<[f64; 2]>> {
+    let n = points.len();
+    if n < 3 {
+        return Err(invalid_gcps(format!(
+            "A thin plate spline needs at least 3 GCPs, found {}",
+            n
+        )));
+    }
+    let mut matrix = vec![vec![0.0; n + 3]; n + 3];
+    for (i, &(x, y)) in points.iter().enumerate() {
+        let terms = thin_plate_spline_terms(points, x, y);
+        for (j, term) in terms.into_iter().enumerate() {
+            matrix[i][j] = term;
+            // The affine part is symmetric.
+            if j >= n {
+                matrix[j][i] = term;
+            }
+        }
+    }
+    let rhs = targets.iter().copied().chain([[0.0; 2]; 3]).collect();
+    solve(matrix, rhs)
+}
+
+/// Solves the linear equation system `matrix` * x = `rhs` (for two right hand sides at once)
+/// by Gaussian elimination with partial pivoting.
+fn solve(mut matrix: Vec<Vec<f64>>, mut rhs: Vec<[f64; 2]>) -> Result<Vec<[f64; 2]>> {
+    let n = matrix.len();
+    // Pivots that are tiny compared to the largest entry mean the system is (nearly) singular.
+    let largest = matrix
+        .iter()
+        .flatten()
+        .fold(0.0_f64, |largest, value| largest.max(value.abs()));
+    let epsilon = largest * 1e-12;
+    for column in 0..n {
+        let pivot = (column..n)
+            .max_by(|&a, &b| matrix[a][column].abs().total_cmp(&matrix[b][column].abs()))
+            .unwrap_or(column);
+        let pivot_value = matrix[pivot][column].abs();
+        if pivot_value.is_nan() || pivot_value <= epsilon {
+            return Err(invalid_gcps(
+                "The GCPs do not determine a transform, e.g., because they are on a line"
+                    .to_string(),
+            ));
+        }
+        matrix.swap(column, pivot);
+        rhs.swap(column, pivot);
+        let (pivot_rows, rows) = matrix.split_at_mut(column + 1);
+        let pivot_row = &pivot_rows[column];
+        let pivot_rhs = rhs[column];
+        for (row, row_rhs) in rows.iter_mut().zip(&mut rhs[column + 1..]) {
+            let factor = row[column] / pivot_row[column];
+            if factor == 0.0 {
+                continue;
+            }
+            for (value, pivot_value) in row[column..].iter_mut().zip(&pivot_row[column..]) {
+                *value -= factor * pivot_value;
+            }
+            row_rhs[0] -= factor * pivot_rhs[0];
+            row_rhs[1] -= factor * pivot_rhs[1];
+        }
+    }
+    let mut solution = vec![[0.0; 2]; n];
+    for row in (0..n).rev() {
+        let mut value = rhs[row];
+        for k in row + 1..n {
+            value[0] -= matrix[row][k] * solution[k][0];
+            value[1] -= matrix[row][k] * solution[k][1];
+        }
+        solution[row] = [value[0] / matrix[row][row], value[1] / matrix[row][row]];
+    }
+    Ok(solution)
+}
+
+fn invalid_gcps(msg: String) -> GeoTiffError {
+    GeoTiffError::InvalidTagValue(TIFFTag::ModelTiepointTag, msg)
+}
@@ -0,0 +1,257 @@
+use std::sync::OnceLock;
+
+use error::{GeoTiffError, Result};
+use gcp::{Gcp, GcpTransform, GcpTransformMethod};
+use lowlevel::TIFFTag;
+use tiff::IFD;
+
+/// An affine transformation from raster space (column, row) to model space (x, y), with the
+/// same six coefficients GDAL uses:
+///
+/// ```text
+/// x = c[0] + column * c[1] + row * c[2]
+/// y = c[3] + column * c[4] + row * c[5]
+/// ```
+///
+/// `c[2]` and `c[4]` are zero unless the raster is rotated or sheared. Raster coordinates refer
+/// to the top left corner of a pixel, i.e., the centre of the first pixel is at (0.5, 0.5).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoTransform(pub [f64; 6]);
+
+impl GeoTransform {
+    /// Creates the transform of a raster without rotation, given the model coordinates
+    /// (`x`, `y`) of the raster position (`i`, `j`) and the size of a pixel in model units.
+    ///
+    /// The raster's y axis points down, while the model's y axis points up, hence the scale in
+    /// y direction is negated.
+    pub fn from_tiepoint_and_scale(tiepoint: &[f64; 6], scale: &[f64; 3]) -> GeoTransform {
+        let [i, j, _, x, y, _] = *tiepoint;
+        let [scale_x, scale_y, _] = *scale;
+        GeoTransform([
+            x - i * scale_x,
+            scale_x,
+            0.0,
+            y + j * scale_y,
+            0.0,
+            -scale_y,
+        ])
+    }
+
+    /// Creates the transform from the 4x4 matrix of a ModelTransformation tag, given in row
+    /// major order. Only the parts of the matrix acting on x and y are used.
+    pub fn from_model_transformation(matrix: &[f64; 16]) -> GeoTransform {
+        GeoTransform([
+            matrix[3], matrix[0], matrix[1], matrix[7], matrix[4], matrix[5],
+        ])
+    }
+
+    /// Transforms raster coordinates (`column`, `row`) into model coordinates (x, y).
+    pub fn apply(&self, column: f64, row: f64) -> (f64, f64) {
+        let c = &self.0;
+        (
+            c[0] + column * c[1] + row * c[2],
+            c[3] + column * c[4] + row * c[5],
+        )
+    }
+
+    /// The inverse transform, i.e., from model to raster coordinates, or `None` if the
+    /// transform is degenerate (e.g., a pixel scale of 0).
+    pub fn inverse(&self) -> Option<GeoTransform> {
+        let c = &self.0;
+        let determinant = c[1] * c[5] - c[2] * c[4];
+        if determinant == 0.0 || !determinant.is_finite() {
+            return None;
+        }
+        let (a, b) = (c[5] / determinant, -c[2] / determinant);
+        let (d, e) = (-c[4] / determinant, c[1] / determinant);
+        Some(GeoTransform([
+            -c[0] * a - c[3] * b,
+            a,
+            b,
+            -c[0] * d - c[3] * e,
+            d,
+            e,
+        ]))
+    }
+
+    /// Reads the transform from the georeferencing tags of an IFD.
+    ///
+    /// A ModelTransformation tag takes precedence over tiepoints. Tiepoints only define an
+    /// affine transform together with a pixel scale, in which case the first tiepoint is used.
+    /// Returns `None` if the tags do not define an affine transform.
+    ///
+    /// For PixelIsPoint rasters, the tags refer to the centre of a pixel. Like GDAL, the
+    /// transform is then shifted by half a pixel so that it refers to the corner of a pixel, as
+    /// for PixelIsArea rasters. `point_geo_ignore` skips this shift, which is what GDAL does with
+    /// `GTIFF_POINT_GEO_IGNORE=TRUE`.
+    pub fn from_ifd(ifd: &IFD, point_geo_ignore: bool) -> Result<Option<GeoTransform>> {
+        let transform = match GeoTransform::from_tags(ifd)? {
+            Some(transform) => transform,
+            None => return Ok(None),
+        };
+        if point_geo_ignore || ifd.get_raster_type() == RasterType::PixelIsArea {
+            return Ok(Some(transform));
+        }
+        let (x, y) = transform.apply(-0.5, -0.5);
+        let c = &transform.0;
+        Ok(Some(GeoTransform([x, c[1], c[2], y, c[4], c[5]])))
+    }
+
+    /// Reads the transform as given by the tags, without considering the raster type.
+    fn from_tags(ifd: &IFD) -> Result<Option<GeoTransform>> {
+        if let Some(matrix) = get_doubles(ifd, TIFFTag::ModelTransformationTag)? {
+            let matrix = first_values::<16>(&matrix, TIFFTag::ModelTransformationTag)?;
+            return Ok(Some(GeoTransform::from_model_transformation(&matrix)));
+        }
+
+        let tiepoints = get_doubles(ifd, TIFFTag::ModelTiepointTag)?;
+        let scale = get_doubles(ifd, TIFFTag::ModelPixelScaleTag)?;
+        match (tiepoints, scale) {
+            (Some(tiepoints), Some(scale)) => {
+                let tiepoint = first_values::<6>(&tiepoints, TIFFTag::ModelTiepointTag)?;
+                let scale = first_values::<3>(&scale, TIFFTag::ModelPixelScaleTag)?;
+                Ok(Some(GeoTransform::from_tiepoint_and_scale(
+                    &tiepoint, &scale,
+                )))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// The transforms of an image between raster and model space, derived once from the tags of the
+/// image, so that converting many coordinates neither parses the tags nor inverts or fits the
+/// transforms again.
+pub(crate) struct Georeference {
+    /// The `point_geo_ignore` setting the transforms were derived with.
+    pub point_geo_ignore: bool,
+    /// The `gcp_transform_method` setting the transforms were derived with.
+    pub gcp_transform_method: GcpTransformMethod,
+    pub transform: Option<GeoTransform>,
+    pub inverse: Option<GeoTransform>,
+    /// The transform fitted to the GCPs (or the error of fitting it), once it has been used.
+    gcp_transform: OnceLock<Result<Option<GcpTransform>>>,
+}
+
+impl Georeference {
+    pub fn new(
+        ifd: &IFD,
+        point_geo_ignore: bool,
+        gcp_transform_method: GcpTransformMethod,
+    ) -> Result<Georeference> {
+        let transform = GeoTransform::from_ifd(ifd, point_geo_ignore)?;
+        Ok(Georeference {
+            point_geo_ignore,
+            gcp_transform_method,
+            transform,
+            inverse: transform.and_then(|transform| transform.inverse()),
+            gcp_transform: OnceLock::new(),
+        })
+    }
+
+    /// The transform fitted to the GCPs of `ifd`, which is fitted on first use. Images
+    /// georeferenced by an affine transform (or not at all) have none, and so do images with a
+    /// single tiepoint but no pixel scale.
+    pub fn gcp_transform(&self, ifd: &IFD) -> Result<Option<&GcpTransform>> {
+        if self.transform.is_some() {
+            return Ok(None);
+        }
+        let transform = self.gcp_transform.get_or_init(|| {
+            let gcps = Gcp::from_ifd(ifd, self.point_geo_ignore)?;
+            if gcps.len() < 2 {
+                return Ok(None);
+            }
+            GcpTransform::new(&gcps, self.gcp_transform_method).map(Some)
+        });
+        match *transform {
+            Ok(ref transform) => Ok(transform.as_ref()),
+            Err(ref err) => Err(err.duplicate()),
+        }
+    }
+}
+
+/// An axis aligned bounding box in model coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl BoundingBox {
+    /// The smallest bounding box containing all `points`.
+    pub fn from_points(points: &[(f64, f64)]) -> BoundingBox {
+        points.iter().fold(
+            BoundingBox {
+                min_x: f64::INFINITY,
+                min_y: f64::INFINITY,
+                max_x: f64::NEG_INFINITY,
+                max_y: f64::NEG_INFINITY,
+            },
+            |bbox, &(x, y)| BoundingBox {
+                min_x: bbox.min_x.min(x),
+                min_y: bbox.min_y.min(y),
+                max_x: bbox.max_x.max(x),
+                max_y: bbox.max_y.max(y),
+            },
+        )
+    }
+
+    pub fn width(&self) -> f64 {
+        self.max_x - self.min_x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.max_y - self.min_y
+    }
+
+    /// Whether the point lies within the bounding box (including its border).
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.min_x && x <= self.max_x && y >= self.min_y && y <= self.max_y
+    }
+}
+
+/// Whether the model coordinates of a raster position refer to the area of a pixel or to a point
+/// at its centre, as given by the GTRasterTypeGeoKey.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RasterType {
+    PixelIsArea,
+    PixelIsPoint,
+}
+
+impl RasterType {
+    /// Decodes the value of the GTRasterTypeGeoKey. Like GDAL, anything but PixelIsPoint (2) is
+    /// treated as PixelIsArea (1).
+    pub fn new(value: u16) -> RasterType {
+        match value {
+            2 => RasterType::PixelIsPoint,
+            _ => RasterType::PixelIsArea,
+        }
+    }
+}
+
+/// Gets the doubles of a tag, or `None` if the tag is missing.
+fn get_doubles(ifd: &IFD, tag: TIFFTag) -> Result<Option<Vec<f64>>> {
+    match ifd.get(tag) {
+        Some(entry) => entry
+            .value
+            .as_doubles()
+            .map(Some)
+            .ok_or(GeoTiffError::InvalidTagType(tag, entry.tpe)),
+        None => Ok(None),
+    }
+}
+
+/// Gets the first `N` values of a tag.
+fn first_values<const N: usize>(values: &[f64], tag: TIFFTag) -> Result<[f64; N]> {
+    let mut result = [0.0; N];
+    let first = values.get(..N).ok_or_else(|| {
+        GeoTiffError::InvalidTagValue(
+            tag,
+            format!("Expected at least {} values, found {}", N, values.len()),
+        )
+    })?;
+    result.copy_from_slice(first);
+    Ok(result)
+}
@@ -0,0 +1,284 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::time::Duration;
+
+use reader::{SeekableReader, MAX_PREALLOCATION};
+
+/// The bytes at the start of a file that are fetched when opening it. For Cloud Optimized
+/// GeoTIFFs, this usually covers the header and all IFDs.
+const HEADER_SIZE: u64 = 16 << 10;
+/// The least number of bytes fetched when a read misses the fetched ranges.
+const READ_AHEAD: u64 = 16 << 10;
+/// Ranges to prefetch that are at most this far apart are fetched by a single request, as
+/// the superfluous bytes cost less than another round trip.
+const MAX_GAP: u64 = 16 << 10;
+/// The number of fetched bytes that are kept. Beyond it, the least recently used ranges are
+/// dropped, except for those of the current prefetch.
+const MAX_CACHED: usize = 64 << 20;
+/// The largest file that is read from a server that doesn't support range requests. Such a
+/// server sends the whole file at once, which is then kept in memory.
+const MAX_UNRANGED_LEN: u64 = MAX_CACHED as u64;
+/// The time to connect to the server.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// The time to wait for the headers of a response once the request is sent.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+/// The time to receive the body of a response. Reads hold the lock of the source, so a stalled
+/// server must not block them forever.
+const BODY_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Reads a remote file by HTTP range requests, fetching only the parts of it that are read.
+///
+/// The start of the file is fetched right away, and later reads fetch at least `READ_AHEAD`
+/// bytes, so the header and the IFDs take few requests. The strips and tiles of a window are
+/// announced by `prefetch`, which fetches neighbouring ones by a single request.
+pub struct HttpReader {
+    agent: ureq::Agent,
+    url: String,
+    len: u64,
+    position: u64,
+    /// The fetched ranges by their offset. Ranges may overlap.
+    chunks: BTreeMap<u64, Chunk>,
+    cached: usize,
+    /// Increases with every access, so that older accesses have smaller ticks.
+    tick: u64,
+    /// The offsets of the fetched ranges by the tick of their last access.
+    recency: BTreeMap<u64, u64>,
+    /// Ranges accessed since this tick are not dropped, which keeps the ranges of the current
+    /// prefetch until they are read.
+    pinned_since: u64,
+}
+
+/// A fetched range.
+struct Chunk {
+    /// The tick of the last access.
+    tick: u64,
+    data: Vec<u8>,
+}
+
+impl HttpReader {
+    /// Opens the file at `url`, fetching its start to learn its length. Servers that don't
+    /// support range requests send the whole file, which is then kept in memory, unless it is
+    /// larger than `MAX_UNRANGED_LEN`.
+    pub fn open(url: &str) -> io::Result<HttpReader> {
+        let config = ureq::Agent::config_builder()
+            .timeout_connect(Some(CONNECT_TIMEOUT))
+            .timeout_recv_response(Some(RESPONSE_TIMEOUT))
+            .timeout_recv_body(Some(BODY_TIMEOUT))
+            .build();
+        let mut reader = HttpReader {
+            agent: ureq::Agent::new_with_config(config),
+            url: url.to_string(),
+            len: 0,
+            position: 0,
+            chunks: BTreeMap::new(),
+            cached: 0,
+            tick: 0,
+            recency: BTreeMap::new(),
+            pinned_since: u64::MAX,
+        };
+        let response = reader
+            .agent
+            .get(url)
+            .header("Range", format!("bytes=0-{}", HEADER_SIZE - 1))
+            .call()
+            .map_err(ureq::Error::into_io)?;
+        let (len, limit) = match response.status().as_u16() {
+            206 => (Some(content_range(&response)?.1), HEADER_SIZE),
+            200 => (None, MAX_UNRANGED_LEN + 1),
+            status => return Err(unexpected_status(status)),
+        };
+        let mut body = Vec::new();
+        response
+            .into_body()
+            .into_reader()
+            .take(limit)
+            .read_to_end(&mut body)?;
+        if body.len() as u64 > MAX_UNRANGED_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Server does not support range requests, and the file exceeds {} bytes",
+                    MAX_UNRANGED_LEN
+                ),
+            ));
+        }
+        reader.len = len.unwrap_or(body.len() as u64);
+        reader.insert(0, body);
+        Ok(reader)
+    }
+
+    /// Finds a fetched range containing the byte at `position`.
+    fn find(&self, position: u64) -> Option<(u64, &[u8])> {
+        self.chunks
+            .range(..=position)
+            .rev()
+            .find(|(&start, chunk)| position < start + chunk.data.len() as u64)
+            .map(|(&start, chunk)| (start, &chunk.data[..]))
+    }
+
+    /// Marks the range at `start` as accessed just now.
+    fn touch(&mut self, start: u64) {
+        if let Some(chunk) = self.chunks.get_mut(&start) {
+            self.tick += 1;
+            self.recency.remove(&chunk.tick);
+            self.recency.insert(self.tick, start);
+            chunk.tick = self.tick;
+        }
+    }
+
+    /// Keeps the range at `start`, dropping the least recently used ranges if needed. Pinned
+    /// ranges are kept even if they exceed `MAX_CACHED`.
+    fn insert(&mut self, start: u64, data: Vec<u8>) {
+        while self.cached + data.len() > MAX_CACHED {
+            let (tick, oldest) = match self.recency.first_key_value() {
+                Some((&tick, &oldest)) if tick < self.pinned_since => (tick, oldest),
+                _ => break,
+            };
+            self.recency.remove(&tick);
+            if let Some(chunk) = self.chunks.remove(&oldest) {
+                self.cached -= chunk.data.len();
+            }
+        }
+        self.tick += 1;
+        self.cached += data.len();
+        self.recency.insert(self.tick, start);
+        let chunk = Chunk {
+            tick: self.tick,
+            data,
+        };
+        if let Some(chunk) = self.chunks.insert(start, chunk) {
+            self.recency.remove(&chunk.tick);
+            self.cached -= chunk.data.len();
+        }
+    }
+
+    /// Fetches the bytes from `start` up to `end` (exclusive).
+    fn fetch(&mut self, start: u64, end: u64) -> io::Result<()> {
+        let response = self
+            .agent
+            .get(&self.url)
+            .header("Range", format!("bytes={}-{}", start, end - 1))
+            .call()
+            .map_err(ureq::Error::into_io)?;
+        match response.status().as_u16() {
+            206 if content_range(&response)?.0 == start => {}
+            206 => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Server sent a different range than requested",
+                ))
+            }
+            status => return Err(unexpected_status(status)),
+        }
+        let len = end - start;
+        let mut chunk = Vec::with_capacity(len.min(MAX_PREALLOCATION) as usize);
+        response
+            .into_body()
+            .into_reader()
+            .take(len)
+            .read_to_end(&mut chunk)?;
+        if (chunk.len() as u64) < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.insert(start, chunk);
+        Ok(())
+    }
+
+    /// Fetches the ranges (offset and length) that were not fetched yet, and marks the others as
+    /// accessed.
+    fn fetch_missing(&mut self, ranges: &[(u64, u64)]) -> io::Result<()> {
+        let mut missing = Vec::new();
+        for &(offset, len) in ranges {
+            let start = offset.min(self.len);
+            let end = offset.saturating_add(len).min(self.len);
+            match self.find(start) {
+                _ if start >= end => {}
+                Some((first, chunk)) if end <= first + chunk.len() as u64 => self.touch(first),
+                _ => missing.push((start, end)),
+            }
+        }
+        missing.sort_unstable();
+        let mut merged: Vec<(u64, u64)> = Vec::new();
+        for (start, end) in missing {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 + MAX_GAP => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        for (start, end) in merged {
+            self.fetch(start, end)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the first byte and the length of the file from the `Content-Range` header
+/// of a partial response, e.g. `bytes 0-16383/116066`.
+fn content_range(response: &ureq::http::Response<ureq::Body>) -> io::Result<(u64, u64)> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid Content-Range header");
+    let value = response
+        .headers()
+        .get("Content-Range")
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(invalid)?;
+    let (range, len) = value
+        .strip_prefix("bytes ")
+        .and_then(|value| value.split_once('/'))
+        .ok_or_else(invalid)?;
+    let (first, _) = range.split_once('-').ok_or_else(invalid)?;
+    match (first.parse(), len.parse()) {
+        (Ok(first), Ok(len)) => Ok((first, len)),
+        _ => Err(invalid()),
+    }
+}
+
+fn unexpected_status(status: u16) -> io::Error {
+    io::Error::other(format!("Unexpected HTTP status {}", status))
+}
+
+impl Read for HttpReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.position >= self.len {
+            return Ok(0);
+        }
+        if self.find(self.position).is_none() {
+            let end = (self.position + (buf.len() as u64).max(READ_AHEAD)).min(self.len);
+            self.fetch(self.position, end)?;
+        }
+        let (start, chunk) = self.find(self.position).expect("Range was just fetched");
+        let offset = (self.position - start) as usize;
+        let n = buf.len().min(chunk.len() - offset);
+        buf[..n].copy_from_slice(&chunk[offset..offset + n]);
+        self.touch(start);
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for HttpReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Seek before the start of the file",
+            )
+        })?;
+        Ok(self.position)
+    }
+}
+
+impl SeekableReader for HttpReader {
+    /// Fetches the ranges that were not fetched yet, merging those that are close to each other
+    /// into a single request. None of the ranges are dropped while fetching the others.
+    fn prefetch(&mut self, ranges: &[(u64, u64)]) -> io::Result<()> {
+        self.pinned_since = self.tick + 1;
+        let result = self.fetch_missing(ranges);
+        self.pinned_since = u64::MAX;
+        result
+    }
+}
@@ -3,18 +3,47 @@
 extern crate byteorder;
 #[macro_use]
 extern crate enum_primitive;
+extern crate flate2;
+#[cfg(feature = "mmap")]
+extern crate memmap2;
 extern crate num;
+#[cfg(feature = "http")]
+extern crate ureq;
 
 use std::fmt;
-use std::io::Result;
 
+use std::io::{Read, Seek};
 use std::path::Path;
+use std::sync::{Arc, PoisonError};
 
+mod compression;
+pub mod crs;
+pub mod error;
+pub mod gcp;
+pub mod geotransform;
+#[cfg(feature = "http")]
+mod http;
 mod lowlevel;
+mod predictor;
+mod projection;
+pub mod raster;
 mod reader;
+pub mod sample;
+pub mod sampler;
 pub mod tiff;
 
+pub use crs::Crs;
+pub use error::{GeoTiffError, Result};
+pub use gcp::{Gcp, GcpTransform, GcpTransformMethod};
+use geotransform::Georeference;
+pub use geotransform::{BoundingBox, GeoTransform, RasterType};
+use lowlevel::TIFFByteOrder;
+pub use lowlevel::{TIFFTag, TagType};
+pub use raster::{BlockView, Raster, RasterData};
 use reader::*;
+pub use sample::{Sample, SampleType, SampleValue};
+pub use sampler::Sampler;
+use tiff::IFD;
 pub use tiff::TIFF;
 
 /// The GeoTIFF library reads `.tiff` files.
@@ -23,26 +52,354 @@ pub use tiff::TIFF;
 /// As such, other use cases are NOT tested (for now).
 impl TIFF {
     /// Opens a `.tiff` file at the location indicated by `filename`.
+    ///
+    /// Only the header and the IFDs are read, so errors in the image data only surface once it is
+    /// read.
     pub fn open<T: AsRef<Path>>(path: T) -> Result<Box<TIFF>> {
         let tiff_reader = TIFFReader;
         tiff_reader.load(path)
     }
 
-    /// Gets the value at a given coordinate (in pixels).
-    pub fn get_value_at(&self, lon: usize, lat: usize) -> usize {
-        self.image_data[lon][lat][0]
+    /// Opens a TIFF from any seekable reader, e.g. a `Cursor` or a `BufReader<File>`.
+    ///
+    /// Like `open`, only the header and the IFDs are read. The reader is kept to read the image
+    /// data later on.
+    pub fn from_reader<R: Read + Seek + Send + 'static>(reader: R) -> Result<Box<TIFF>> {
+        TIFFReader.read(Box::new(PlainReader(reader)))
+    }
+
+    /// Opens a TIFF held in memory, e.g. a `Vec<u8>` or a `&'static [u8]`, without copying it.
+    pub fn from_bytes<B: AsRef<[u8]> + Send + Sync + 'static>(bytes: B) -> Result<Box<TIFF>> {
+        TIFFReader.read_bytes(SharedBytes(Arc::new(bytes)))
+    }
+
+    /// Opens a `.tiff` file by memory mapping it, so its image data is read without any system
+    /// calls, and uncompressed strips and tiles can be viewed without copying them (see
+    /// `view_block`).
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified (e.g., truncated) by this or another process while it is
+    /// open, as this changes the memory underneath the TIFF.
+    #[cfg(feature = "mmap")]
+    pub unsafe fn open_mmap<T: AsRef<Path>>(path: T) -> Result<Box<TIFF>> {
+        TIFFReader.load_mmap(path)
+    }
+
+    /// Opens a remote `.tiff` file by HTTP range requests, e.g. a Cloud Optimized GeoTIFF in an
+    /// object storage.
+    ///
+    /// Only the start of the file is downloaded right away, to read the header and the IFDs.
+    /// Windows (see `read_window`) then only download their strips or tiles, where neighbouring
+    /// ones are fetched by a single request.
+    #[cfg(feature = "http")]
+    pub fn open_url(url: &str) -> Result<Box<TIFF>> {
+        TIFFReader.load_url(url)
+    }
+
+    /// Opens a TIFF held in a borrowed buffer, which is copied as the TIFF outlives it.
+    pub fn from_slice(bytes: &[u8]) -> Result<Box<TIFF>> {
+        TIFF::from_bytes(bytes.to_vec())
+    }
+
+    /// Gets the value at a given coordinate (in pixels), or `None` if the coordinate lies outside
+    /// of the image.
+    pub fn get_value_at(&self, lon: usize, lat: usize) -> Result<Option<Sample>> {
+        self.read_value_at(lat, lon)
+    }
+
+    /// Reads the value of the first band at pixel (`x`, `y`), i.e., in column `x` and row `y`,
+    /// or `None` if the pixel lies outside of the image.
+    ///
+    /// Unless the image was read as a whole already, only the strip or tile containing the pixel
+    /// is read. For many lookups, a `sampler` avoids reading the same strip or tile repeatedly.
+    pub fn read_value_at(&self, x: usize, y: usize) -> Result<Option<Sample>> {
+        if let Some(data) = self.images[0].get() {
+            return Ok(data.get(x, y, 0));
+        }
+        let image = ImageInfo::new(&self.ifds[0])?;
+        if x >= image.width || y >= image.height {
+            return Ok(None);
+        }
+        Ok(self.read_window(x, y, 1, 1, &[0])?.get(0, 0, 0))
+    }
+
+    /// Gets the image data of the (first) image, which is read as a whole on first access and
+    /// kept in memory afterwards.
+    pub fn image_data(&self) -> Result<&RasterData> {
+        self.get_image_data(0)
+    }
+
+    /// Reads a window of `width` * `height` pixels at (`x_off`, `y_off`) of the (first) image,
+    /// with the samples of the given `bands` (in this order).
+    ///
+    /// Only the strips or tiles intersecting the window are read, so this is much cheaper than
+    /// reading the whole image when only a small part of it is needed.
+    pub fn read_window(
+        &self,
+        x_off: usize,
+        y_off: usize,
+        width: usize,
+        height: usize,
+        bands: &[usize],
+    ) -> Result<RasterData> {
+        let window = Window {
+            x: x_off,
+            y: y_off,
+            width,
+            height,
+            bands: bands.to_vec(),
+        };
+        self.read(&self.ifds[0], &window)
+    }
+
+    /// Creates a sampler for looking up single samples of the (first) image, which keeps up to
+    /// `cache_size` bytes of decoded strips and tiles in memory.
+    ///
+    /// Unlike `get_value_at`, this keeps the strips and tiles it read, which makes it the better
+    /// choice for many lookups in a large image.
+    pub fn sampler(&self, cache_size: usize) -> Result<Sampler<'_>> {
+        Sampler::new(self, 0, cache_size)
+    }
+
+    /// Returns the number of strips or tiles of the (first) image.
+    pub fn block_count(&self) -> Result<usize> {
+        let ifd = &self.ifds[0];
+        let image = ImageInfo::new(ifd)?;
+        Ok(TIFFReader
+            .get_block_layout(ifd, image.width, image.height)?
+            .block_count)
+    }
+
+    /// Views the `index`-th strip or tile of the (first) image directly as samples of type `T`,
+    /// without copying or decoding it.
+    ///
+    /// This is only possible if the TIFF is held in memory (see `open_mmap` and `from_bytes`),
+    /// the block is neither compressed nor uses a predictor, `T` matches the sample type, the
+    /// file's byte order is the native one, and the block is aligned for `T` within the file.
+    /// Otherwise, `None` is returned, and the block has to be read by `read_window`.
+    pub fn view_block<T: SampleValue>(&self, index: usize) -> Result<Option<BlockView<'_, T>>> {
+        let bytes = match self.bytes {
+            Some(ref bytes) => bytes.as_ref(),
+            None => return Ok(None),
+        };
+        let ifd = &self.ifds[0];
+        let image = ImageInfo::new(ifd)?;
+        let layout = TIFFReader.get_block_layout(ifd, image.width, image.height)?;
+        if index >= layout.block_count {
+            return Err(GeoTiffError::InvalidWindow(format!(
+                "Block {} does not exist, the image has {} blocks",
+                index, layout.block_count
+            )));
+        }
+        let native = match self.byte_order {
+            TIFFByteOrder::LittleEndian => cfg!(target_endian = "little"),
+            TIFFByteOrder::BigEndian => cfg!(target_endian = "big"),
+        };
+        if image.sample_type != T::SAMPLE_TYPE || !(native || image.sample_type.size() == 1) {
+            return Ok(None);
+        }
+        let raw = match layout.raw_block(bytes, &image, index)? {
+            Some(raw) => raw,
+            None => return Ok(None),
+        };
+        // Safe, as `SampleValue` is only implemented for primitive numbers, which are valid for
+        // any bit pattern.
+        let (prefix, samples, _) = unsafe { raw.align_to::<T>() };
+        if !prefix.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(BlockView {
+            x: (index % layout.blocks_across) * layout.block_width,
+            y: (index / layout.blocks_across) * layout.block_height,
+            width: layout.block_width,
+            height: layout.rows(&image, index),
+            bands: image.samples_per_pixel,
+            samples,
+        }))
+    }
+
+    /// Reads a window of the image described by `ifd` from the file.
+    fn read(&self, ifd: &IFD, window: &Window) -> Result<RasterData> {
+        // A panic while reading leaves the reader at some position, which is fine as every read
+        // seeks first.
+        let mut source = self.source.lock().unwrap_or_else(PoisonError::into_inner);
+        TIFFReader.read_window(&mut **source, self.byte_order, ifd, window)
+    }
+
+    /// Gets the affine transform from pixel to model coordinates of the (first) image, or `None`
+    /// if the image is not georeferenced by an affine transform.
+    ///
+    /// The transform always refers to the corner of a pixel, also for PixelIsPoint images
+    /// (unless `point_geo_ignore` is set).
+    pub fn get_geo_transform(&self) -> Result<Option<GeoTransform>> {
+        Ok(self.georeference()?.transform)
+    }
+
+    /// Gets the georeference of the (first) image, which is derived from its tags on first use,
+    /// and again whenever `point_geo_ignore` or `gcp_transform_method` changed since.
+    fn georeference(&self) -> Result<Arc<Georeference>> {
+        let mut cached = self
+            .georeference
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match *cached {
+            Some(ref georeference)
+                if georeference.point_geo_ignore == self.point_geo_ignore
+                    && georeference.gcp_transform_method == self.gcp_transform_method =>
+            {
+                Ok(georeference.clone())
+            }
+            _ => {
+                let georeference = Arc::new(Georeference::new(
+                    &self.ifds[0],
+                    self.point_geo_ignore,
+                    self.gcp_transform_method,
+                )?);
+                *cached = Some(georeference.clone());
+                Ok(georeference)
+            }
+        }
+    }
+
+    /// Gets the raster type of the (first) image.
+    pub fn get_raster_type(&self) -> RasterType {
+        self.ifds[0].get_raster_type()
+    }
+
+    /// Gets the coordinate reference system of the model space of the (first) image, as
+    /// described by its GeoKeys.
+    pub fn get_crs(&self) -> Result<Crs> {
+        Crs::from_geo_keys(&self.ifds[0].get_geo_keys()?)
+    }
+
+    /// Gets the tiepoints of the (first) image as ground control points.
+    pub fn get_gcps(&self) -> Result<Vec<Gcp>> {
+        Gcp::from_ifd(&self.ifds[0], self.point_geo_ignore)
+    }
+
+    /// Gets the transform fitted to the ground control points using `gcp_transform_method`, or
+    /// `None` if the image is not georeferenced by GCPs but by an affine transform (or not at
+    /// all).
+    pub fn get_gcp_transform(&self) -> Result<Option<GcpTransform>> {
+        Ok(self.georeference()?.gcp_transform(&self.ifds[0])?.cloned())
+    }
+
+    /// Converts raster coordinates into model coordinates, where (0, 0) is the top left corner
+    /// of the top left pixel and (0.5, 0.5) is its centre.
+    ///
+    /// For images georeferenced by GCPs, the transform is fitted on the first call only.
+    pub fn pixel_to_model(&self, col: f64, row: f64) -> Result<(f64, f64)> {
+        let georeference = self.georeference()?;
+        if let Some(transform) = georeference.transform {
+            return Ok(transform.apply(col, row));
+        }
+        let transform = georeference
+            .gcp_transform(&self.ifds[0])?
+            .ok_or(GeoTiffError::NoGeoTransform)?;
+        Ok(transform.pixel_to_model(col, row))
+    }
+
+    /// Converts model coordinates into (fractional) raster coordinates, the inverse of
+    /// `pixel_to_model`.
+    pub fn model_to_pixel(&self, x: f64, y: f64) -> Result<(f64, f64)> {
+        let georeference = self.georeference()?;
+        if georeference.transform.is_some() {
+            let inverse = georeference.inverse.ok_or(GeoTiffError::NoGeoTransform)?;
+            return Ok(inverse.apply(x, y));
+        }
+        let transform = georeference
+            .gcp_transform(&self.ifds[0])?
+            .ok_or(GeoTiffError::NoGeoTransform)?;
+        Ok(transform.model_to_pixel(x, y))
+    }
+
+    /// Gets the model coordinates of the four corners of the (first) image, in the order top
+    /// left, top right, bottom right, bottom left (in raster space).
+    ///
+    /// Only the tags are used, so this works without looking at any pixels. For rotated or
+    /// sheared images, the corners describe the footprint more closely than the bounding box.
+    pub fn get_corners(&self) -> Result<[(f64, f64); 4]> {
+        let ifd = &self.ifds[0];
+        let width = ifd.get_image_width()? as f64;
+        let height = ifd.get_image_length()? as f64;
+        let corners = [(0.0, 0.0), (width, 0.0), (width, height), (0.0, height)];
+        let georeference = self.georeference()?;
+        if let Some(transform) = georeference.transform {
+            return Ok(corners.map(|(col, row)| transform.apply(col, row)));
+        }
+        let transform = georeference
+            .gcp_transform(&self.ifds[0])?
+            .ok_or(GeoTiffError::NoGeoTransform)?;
+        Ok(corners.map(|(col, row)| transform.pixel_to_model(col, row)))
+    }
+
+    /// Gets the bounding box of the (first) image in model coordinates, computed from its
+    /// corners.
+    pub fn get_bounding_box(&self) -> Result<BoundingBox> {
+        Ok(BoundingBox::from_points(&self.get_corners()?))
+    }
+
+    /// Gets the value of the first band at the given model coordinates, or `None` if they lie
+    /// outside of the image, the image is not georeferenced or its data can't be read.
+    ///
+    /// Like `read_value_at`, only the strip or tile containing the coordinates is read.
+    pub fn get_value_at_coord(&self, x: f64, y: f64) -> Option<Sample> {
+        let (col, row) = self.model_to_pixel(x, y).ok()?;
+        if !(col >= 0.0 && row >= 0.0) {
+            return None;
+        }
+        self.read_value_at(col.floor() as usize, row.floor() as usize)
+            .ok()?
+    }
+
+    /// Gets the value of the first band at the given WGS 84 longitude and latitude (in degrees),
+    /// or `None` if they lie outside of the image or the image's CRS is not supported.
+    ///
+    /// The CRS is resolved from the GeoKeys on the first call only.
+    pub fn get_value_at_lonlat(&self, lon: f64, lat: f64) -> Option<Sample> {
+        let crs = self.crs.get_or_init(|| self.get_crs().ok()).as_ref()?;
+        let (x, y) = crs.from_wgs84(lon, lat).ok()?;
+        self.get_value_at_coord(x, y)
+    }
+
+    /// Returns the number of images (one per IFD) within this file.
+    pub fn image_count(&self) -> usize {
+        self.ifds.len()
+    }
+
+    /// Gets the image data of the `index`-th IFD, where index 0 is the first image. Like
+    /// `image_data`, the image is read as a whole on first access.
+    pub fn get_image_data(&self, index: usize) -> Result<&RasterData> {
+        let (ifd, image) = self
+            .ifds
+            .get(index)
+            .zip(self.images.get(index))
+            .ok_or(GeoTiffError::NoSuchImage(index))?;
+        if let Some(data) = image.get() {
+            return Ok(data);
+        }
+        let data = self.read(ifd, &Window::full(ifd)?)?;
+        Ok(image.get_or_init(|| data))
+    }
+
+    /// Gets the value at a given coordinate (in pixels) of the `index`-th image, or `None` if
+    /// there is no such image or pixel.
+    pub fn get_value_of_image_at(&self, index: usize, lon: usize, lat: usize) -> Option<Sample> {
+        self.get_image_data(index).ok()?.get(lat, lon, 0)
     }
 }
 
 /// Overwrite default display function.
 impl fmt::Display for TIFF {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ifd = &self.ifds[0];
         write!(
             f,
             "TIFF(Image size: [{}, {}, {}], Tag data: {:?})",
-            self.image_data.len(),
-            self.image_data[0].len(),
-            self.image_data[0][0].len(),
+            ifd.get_image_length().unwrap_or(0),
+            ifd.get_image_width().unwrap_or(0),
+            ifd.get_samples_per_pixel().unwrap_or(0),
             self.ifds
         )
     }
@@ -12,20 +12,54 @@ pub type SignedLong = i32;
 pub type SignedRational = (i32, i32);
 pub type Float = f32;
 pub type Double = f64;
+pub type Long8 = u64;
+pub type SignedLong8 = i64;
 
 // Different values individual components can take.
 enum_from_primitive! {
     #[repr(u16)]
-    #[derive(Debug)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
     pub enum TIFFByteOrder {
         LittleEndian = 0x4949,
         BigEndian    = 0x4d4d,
     }
 }
 
+/// The flavour of a TIFF file, as indicated by the magic number in the header. BigTIFF (43) uses
+/// 64-bit offsets and counts, and consequently larger IFD entries than classic TIFF (42).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TIFFVariant {
+    Classic,
+    BigTIFF,
+}
+
+impl TIFFVariant {
+    /// Size of an offset (and of the value field of an IFD entry) in bytes.
+    pub fn offset_size(&self) -> u64 {
+        match *self {
+            TIFFVariant::Classic => 4,
+            TIFFVariant::BigTIFF => 8,
+        }
+    }
+
+    /// Size of the number of entries at the start of an IFD in bytes.
+    pub fn entry_count_size(&self) -> u64 {
+        match *self {
+            TIFFVariant::Classic => 2,
+            TIFFVariant::BigTIFF => 8,
+        }
+    }
+
+    /// Size of a single IFD entry in bytes.
+    pub fn entry_size(&self) -> u64 {
+        // Tag ID and field type (2 bytes each), followed by the count and the value field.
+        4 + 2 * self.offset_size()
+    }
+}
+
 enum_from_primitive! {
     #[repr(u16)]
-    #[derive(Debug,PartialEq,Clone)]
+    #[derive(Debug, PartialEq, Clone, Copy)]
     pub enum TagType {
         Byte           = 1,
         ASCII          = 2,
@@ -39,6 +73,10 @@ enum_from_primitive! {
         SignedRational = 10,
         Float          = 11,
         Double         = 12,
+        IFD            = 13,
+        Long8          = 16,
+        SignedLong8    = 17,
+        IFD8           = 18,
     }
 }
 
@@ -53,10 +91,14 @@ pub fn tag_size(t: &TagType) -> u32 {
         TagType::SignedByte => 1,
         TagType::Undefined => 1,
         TagType::SignedShort => 2,
-        TagType::SignedLong => 2,
+        TagType::SignedLong => 4,
         TagType::SignedRational => 8,
         TagType::Float => 4,
         TagType::Double => 8,
+        TagType::IFD => 4,
+        TagType::Long8 => 8,
+        TagType::SignedLong8 => 8,
+        TagType::IFD8 => 8,
     }
 }
 
@@ -74,6 +116,8 @@ pub enum TaggedData {
     SignedRational(Vec<SignedRational>),
     Float(Vec<Float>),
     Double(Vec<Double>),
+    Long8(Vec<Long8>),
+    SignedLong8(Vec<SignedLong8>),
 }
 
 impl TaggedData {
@@ -85,11 +129,28 @@ impl TaggedData {
         }
     }
 
+    pub fn as_doubles(&self) -> Option<Vec<f64>> {
+        if let Self::Double(x) = self {
+            Some(x.clone())
+        } else {
+            None
+        }
+    }
+
+    pub fn as_ascii(&self) -> Option<&str> {
+        if let Self::Ascii(x) = self {
+            Some(x)
+        } else {
+            None
+        }
+    }
+
     pub fn as_unsigned_ints(&self) -> Option<Vec<usize>> {
         match self {
             Self::Byte(x) => Some(x.iter().map(|x| *x as usize).collect()),
             Self::Short(x) => Some(x.iter().map(|x| *x as usize).collect()),
             Self::Long(x) => Some(x.iter().map(|x| *x as usize).collect()),
+            Self::Long8(x) => Some(x.iter().map(|x| *x as usize).collect()),
             _ => None,
         }
     }
@@ -99,6 +160,7 @@ impl TaggedData {
             Self::SignedByte(x) => Some(x.iter().map(|x| *x as isize).collect()),
             Self::SignedShort(x) => Some(x.iter().map(|x| *x as isize).collect()),
             Self::SignedLong(x) => Some(x.iter().map(|x| *x as isize).collect()),
+            Self::SignedLong8(x) => Some(x.iter().map(|x| *x as isize).collect()),
             _ => None,
         }
     }
@@ -111,16 +173,31 @@ pub enum PhotometricInterpretation {
     BlackIsZero = 1,
 }
 
-/// The compression chosen for this TIFF.
-#[repr(u16)]
-#[derive(Debug)]
-pub enum Compression {
-    None = 1,
-    Huffman = 2,
-    Lzw = 5,
-    Ojpeg = 6,
-    Jpeg = 7,
-    PackBits = 32773,
+enum_from_primitive! {
+    /// The compression chosen for this TIFF.
+    #[repr(u16)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Compression {
+        None = 1,
+        Huffman = 2,
+        Lzw = 5,
+        Ojpeg = 6,
+        Jpeg = 7,
+        AdobeDeflate = 8,
+        PackBits = 32773,
+        Deflate = 32946,
+    }
+}
+
+enum_from_primitive! {
+    /// The predictor applied to the image data before compression.
+    #[repr(u16)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Predictor {
+        None = 1,
+        HorizontalDifferencing = 2,
+        FloatingPoint = 3,
+    }
 }
 
 /// The resolution unit of this TIFF.
@@ -132,14 +209,16 @@ pub enum ResolutionUnit {
     Centimetre = 3,
 }
 
-/// The sample format of this TIFF.
-#[repr(u16)]
-#[derive(Debug)]
-pub enum SampleFormat {
-    UnsignedInteger = 1,
-    TwosComplementSignedInteger = 2,
-    IEEEFloatingPoint = 3,
-    Undefined = 4,
+enum_from_primitive! {
+    /// The sample format of this TIFF.
+    #[repr(u16)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum SampleFormat {
+        UnsignedInteger = 1,
+        TwosComplementSignedInteger = 2,
+        IEEEFloatingPoint = 3,
+        Undefined = 4,
+    }
 }
 
 /// The image type of this TIFF.
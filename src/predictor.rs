@@ -0,0 +1,130 @@
+use byteorder::ByteOrder;
+
+use error::{GeoTiffError, Result};
+use lowlevel::Predictor;
+
+/// Reverses the predictor that was applied to a decompressed strip or tile.
+///
+/// The data consists of rows of `row_width` pixels (the image width for strips, the tile width
+/// for tiles), each made up of `samples_per_pixel` samples of `bytes_per_sample` bytes.
+pub fn undo_predictor<Endian: ByteOrder>(
+    predictor: &Predictor,
+    data: &mut [u8],
+    row_width: usize,
+    samples_per_pixel: usize,
+    bytes_per_sample: usize,
+) -> Result<()> {
+    match *predictor {
+        Predictor::None => Ok(()),
+        Predictor::HorizontalDifferencing => {
+            let row_len = row_width * samples_per_pixel * bytes_per_sample;
+            for row in data.chunks_exact_mut(row_len) {
+                undo_horizontal_differencing::<Endian>(row, samples_per_pixel, bytes_per_sample)?;
+            }
+            Ok(())
+        }
+        Predictor::FloatingPoint => {
+            let row_len = row_width * samples_per_pixel * bytes_per_sample;
+            let mut shuffled = vec![0; row_len];
+            for row in data.chunks_exact_mut(row_len) {
+                shuffled.copy_from_slice(row);
+                undo_floating_point::<Endian>(
+                    &mut shuffled,
+                    row,
+                    samples_per_pixel,
+                    bytes_per_sample,
+                )?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Undoes the floating point predictor (predictor 3) for a single row.
+///
+/// Before compression, the bytes of all samples in a row were split into byte planes, starting
+/// with the plane of the most significant bytes, and the resulting byte sequence was horizontally
+/// differenced per sample. So the differencing is reversed on `shuffled` byte by byte first, and
+/// then the planes are interleaved back into samples, written to `row` in the file's byte order.
+fn undo_floating_point<Endian: ByteOrder>(
+    shuffled: &mut [u8],
+    row: &mut [u8],
+    samples_per_pixel: usize,
+    bytes_per_sample: usize,
+) -> Result<()> {
+    for i in samples_per_pixel..shuffled.len() {
+        shuffled[i] = shuffled[i].wrapping_add(shuffled[i - samples_per_pixel]);
+    }
+    let samples = shuffled.len() / bytes_per_sample;
+    // Collects the bytes of the n-th sample, most significant byte first.
+    let big_endian_bytes = |n: usize| {
+        let mut bytes = [0; 8];
+        for (plane, byte) in bytes.iter_mut().take(bytes_per_sample).enumerate() {
+            *byte = shuffled[plane * samples + n];
+        }
+        bytes
+    };
+    for (n, sample) in row.chunks_exact_mut(bytes_per_sample).enumerate() {
+        let bytes = big_endian_bytes(n);
+        match bytes_per_sample {
+            2 => Endian::write_u16(sample, u16::from_be_bytes([bytes[0], bytes[1]])),
+            4 => Endian::write_u32(
+                sample,
+                u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            ),
+            8 => Endian::write_u64(sample, u64::from_be_bytes(bytes)),
+            _ => {
+                return Err(GeoTiffError::UnsupportedPredictor(
+                    Predictor::FloatingPoint as u16,
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Undoes horizontal differencing (predictor 2) for a single row.
+///
+/// Every sample except those of the first pixel was stored as the difference to the same sample
+/// of the previous pixel, so the row is restored by a running (wrapping) sum per sample.
+fn undo_horizontal_differencing<Endian: ByteOrder>(
+    row: &mut [u8],
+    samples_per_pixel: usize,
+    bytes_per_sample: usize,
+) -> Result<()> {
+    let stride = samples_per_pixel * bytes_per_sample;
+    match bytes_per_sample {
+        1 => {
+            for i in stride..row.len() {
+                row[i] = row[i].wrapping_add(row[i - stride]);
+            }
+        }
+        2 => {
+            for i in (stride..row.len()).step_by(2) {
+                let value =
+                    Endian::read_u16(&row[i..]).wrapping_add(Endian::read_u16(&row[i - stride..]));
+                Endian::write_u16(&mut row[i..], value);
+            }
+        }
+        4 => {
+            for i in (stride..row.len()).step_by(4) {
+                let value =
+                    Endian::read_u32(&row[i..]).wrapping_add(Endian::read_u32(&row[i - stride..]));
+                Endian::write_u32(&mut row[i..], value);
+            }
+        }
+        8 => {
+            for i in (stride..row.len()).step_by(8) {
+                let value =
+                    Endian::read_u64(&row[i..]).wrapping_add(Endian::read_u64(&row[i - stride..]));
+                Endian::write_u64(&mut row[i..], value);
+            }
+        }
+        _ => {
+            return Err(GeoTiffError::UnsupportedPredictor(
+                Predictor::HorizontalDifferencing as u16,
+            ))
+        }
+    }
+    Ok(())
+}
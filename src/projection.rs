@@ -0,0 +1,751 @@
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+
+use crs::{Crs, Ellipsoid, GeodeticDatum, GeographicCrs, ProjectedCrs, Projection};
+use error::{GeoTiffError, Result};
+
+/// The WGS 84 ellipsoid, which GPS coordinates refer to.
+fn wgs84() -> Ellipsoid {
+    Ellipsoid {
+        name: "WGS 84".to_string(),
+        semi_major_axis: 6378137.0,
+        inverse_flattening: 298.257223563,
+    }
+}
+
+impl Crs {
+    /// Converts WGS 84 longitude and latitude (in degrees) into model coordinates of this CRS.
+    ///
+    /// The datum is shifted using the datum's Helmert parameters, which typically are accurate
+    /// to about a metre. Heights are assumed to be 0.
+    pub fn from_wgs84(&self, lon: f64, lat: f64) -> Result<(f64, f64)> {
+        match *self {
+            Crs::Geographic(ref crs) => {
+                let (lon, lat) = crs.datum.from_wgs84(lon, lat)?;
+                Ok(crs.greenwich_to_model(lon, lat))
+            }
+            Crs::Projected(ref crs) => {
+                let (lon, lat) = crs.base.datum.from_wgs84(lon, lat)?;
+                Ok(crs.project(lon, lat))
+            }
+            Crs::Epsg(code) => Err(unknown_epsg(code)),
+        }
+    }
+
+    /// Converts model coordinates of this CRS into WGS 84 longitude and latitude (in degrees).
+    pub fn to_wgs84(&self, x: f64, y: f64) -> Result<(f64, f64)> {
+        match *self {
+            Crs::Geographic(ref crs) => {
+                let (lon, lat) = crs.model_to_greenwich(x, y);
+                crs.datum.to_wgs84(lon, lat)
+            }
+            Crs::Projected(ref crs) => {
+                let (lon, lat) = crs.unproject(x, y);
+                crs.base.datum.to_wgs84(lon, lat)
+            }
+            Crs::Epsg(code) => Err(unknown_epsg(code)),
+        }
+    }
+}
+
+fn unknown_epsg(code: u16) -> GeoTiffError {
+    GeoTiffError::UnsupportedCrs(format!("The definition of EPSG:{} is not known", code))
+}
+
+impl GeographicCrs {
+    /// Converts longitude and latitude in degrees east of Greenwich into the coordinates of this
+    /// CRS, i.e., relative to its prime meridian and in its angular unit.
+    fn greenwich_to_model(&self, lon: f64, lat: f64) -> (f64, f64) {
+        let factor = self.angular_unit.factor;
+        (
+            (lon - self.prime_meridian.longitude).to_radians() / factor,
+            lat.to_radians() / factor,
+        )
+    }
+
+    /// The inverse of `greenwich_to_model`.
+    fn model_to_greenwich(&self, x: f64, y: f64) -> (f64, f64) {
+        let factor = self.angular_unit.factor;
+        (
+            (x * factor).to_degrees() + self.prime_meridian.longitude,
+            (y * factor).to_degrees(),
+        )
+    }
+}
+
+impl ProjectedCrs {
+    /// Projects longitude and latitude (in degrees east of Greenwich) on the datum of this CRS
+    /// into its easting and northing.
+    pub fn project(&self, lon: f64, lat: f64) -> (f64, f64) {
+        let lon = (lon - self.base.prime_meridian.longitude).to_radians();
+        let (false_easting, false_northing) = self.projection.false_origin();
+        let (dx, dy) = self
+            .projection
+            .forward(&self.base.datum.ellipsoid, lon, lat.to_radians());
+        let factor = self.linear_unit.factor;
+        (false_easting + dx / factor, false_northing + dy / factor)
+    }
+
+    /// The inverse of `project`.
+    pub fn unproject(&self, x: f64, y: f64) -> (f64, f64) {
+        let (false_easting, false_northing) = self.projection.false_origin();
+        let factor = self.linear_unit.factor;
+        let (lon, lat) = self.projection.inverse(
+            &self.base.datum.ellipsoid,
+            (x - false_easting) * factor,
+            (y - false_northing) * factor,
+        );
+        (
+            lon.to_degrees() + self.base.prime_meridian.longitude,
+            lat.to_degrees(),
+        )
+    }
+}
+
+impl GeodeticDatum {
+    /// Shifts WGS 84 longitude and latitude (in degrees) onto this datum.
+    pub fn from_wgs84(&self, lon: f64, lat: f64) -> Result<(f64, f64)> {
+        let parameters = self.helmert_parameters()?;
+        let inverse = parameters.map(|p| -p);
+        let (x, y, z) = to_geocentric(&wgs84(), lon.to_radians(), lat.to_radians());
+        let (x, y, z) = helmert(&inverse, x, y, z);
+        let (lon, lat) = from_geocentric(&self.ellipsoid, x, y, z);
+        Ok((lon.to_degrees(), lat.to_degrees()))
+    }
+
+    /// Shifts longitude and latitude (in degrees) on this datum onto WGS 84.
+    pub fn to_wgs84(&self, lon: f64, lat: f64) -> Result<(f64, f64)> {
+        let parameters = self.helmert_parameters()?;
+        let (x, y, z) = to_geocentric(&self.ellipsoid, lon.to_radians(), lat.to_radians());
+        let (x, y, z) = helmert(&parameters, x, y, z);
+        let (lon, lat) = from_geocentric(&wgs84(), x, y, z);
+        Ok((lon.to_degrees(), lat.to_degrees()))
+    }
+
+    /// The 7 parameters of the transformation to WGS 84, where 3 parameters are extended by
+    /// zero rotation and scale.
+    fn helmert_parameters(&self) -> Result<[f64; 7]> {
+        let mut parameters = [0.0; 7];
+        match self.to_wgs84 {
+            Some(ref values) if values.len() == 3 || values.len() == 7 => {
+                parameters[..values.len()].copy_from_slice(values);
+                Ok(parameters)
+            }
+            _ => Err(GeoTiffError::UnsupportedCrs(format!(
+                "No transformation from datum {} to WGS 84 is known",
+                self.name
+            ))),
+        }
+    }
+}
+
+/// Converts geodetic coordinates (in radians, at height 0) into geocentric ones.
+fn to_geocentric(ellipsoid: &Ellipsoid, lon: f64, lat: f64) -> (f64, f64, f64) {
+    let a = ellipsoid.semi_major_axis;
+    let e2 = ellipsoid.eccentricity_squared();
+    let nu = a / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+    (
+        nu * lat.cos() * lon.cos(),
+        nu * lat.cos() * lon.sin(),
+        nu * (1.0 - e2) * lat.sin(),
+    )
+}
+
+/// Converts geocentric coordinates into geodetic ones (in radians), iterating on the latitude.
+fn from_geocentric(ellipsoid: &Ellipsoid, x: f64, y: f64, z: f64) -> (f64, f64) {
+    let a = ellipsoid.semi_major_axis;
+    let e2 = ellipsoid.eccentricity_squared();
+    let p = x.hypot(y);
+    let mut lat = z.atan2(p * (1.0 - e2));
+    for _ in 0..10 {
+        let nu = a / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+        let next = (z + e2 * nu * lat.sin()).atan2(p);
+        let converged = (next - lat).abs() < 1e-14;
+        lat = next;
+        if converged {
+            break;
+        }
+    }
+    (y.atan2(x), lat)
+}
+
+/// Applies a Helmert transformation with translations in metres, rotations in arc seconds
+/// (position vector convention) and scale in parts per million.
+fn helmert(parameters: &[f64; 7], x: f64, y: f64, z: f64) -> (f64, f64, f64) {
+    let [tx, ty, tz, rx, ry, rz, ds] = *parameters;
+    let arc_second = (1.0_f64 / 3600.0).to_radians();
+    let (rx, ry, rz) = (rx * arc_second, ry * arc_second, rz * arc_second);
+    let scale = 1.0 + ds * 1e-6;
+    (
+        tx + scale * (x - rz * y + ry * z),
+        ty + scale * (rz * x + y - rx * z),
+        tz + scale * (-ry * x + rx * y + z),
+    )
+}
+
+/// The isometric latitude function t of the EPSG guidance note, used by the conformal
+/// projections.
+fn conformal_t(lat: f64, e: f64) -> f64 {
+    let sin = lat.sin();
+    (FRAC_PI_4 - lat / 2.0).tan() / ((1.0 - e * sin) / (1.0 + e * sin)).powf(e / 2.0)
+}
+
+/// The inverse of `conformal_t`, by fixed point iteration.
+fn latitude_from_t(t: f64, e: f64) -> f64 {
+    let mut lat = FRAC_PI_2 - 2.0 * t.atan();
+    for _ in 0..15 {
+        let sin = lat.sin();
+        let next = FRAC_PI_2 - 2.0 * (t * ((1.0 - e * sin) / (1.0 + e * sin)).powf(e / 2.0)).atan();
+        let converged = (next - lat).abs() < 1e-14;
+        lat = next;
+        if converged {
+            break;
+        }
+    }
+    lat
+}
+
+/// The m function of the EPSG guidance note.
+fn m(lat: f64, e2: f64) -> f64 {
+    lat.cos() / (1.0 - e2 * lat.sin().powi(2)).sqrt()
+}
+
+impl Projection {
+    /// The false easting and northing, in the linear unit of the CRS.
+    fn false_origin(&self) -> (f64, f64) {
+        match *self {
+            Projection::TransverseMercator {
+                false_easting,
+                false_northing,
+                ..
+            }
+            | Projection::Mercator {
+                false_easting,
+                false_northing,
+                ..
+            }
+            | Projection::WebMercator {
+                false_easting,
+                false_northing,
+                ..
+            }
+            | Projection::LambertConformalConic1SP {
+                false_easting,
+                false_northing,
+                ..
+            }
+            | Projection::LambertConformalConic2SP {
+                false_easting,
+                false_northing,
+                ..
+            }
+            | Projection::AlbersEqualArea {
+                false_easting,
+                false_northing,
+                ..
+            }
+            | Projection::ObliqueMercatorA {
+                false_easting,
+                false_northing,
+                ..
+            }
+            | Projection::ObliqueMercator {
+                false_easting,
+                false_northing,
+                ..
+            } => (false_easting, false_northing),
+        }
+    }
+
+    /// Projects longitude and latitude (in radians, relative to the prime meridian) into metres
+    /// relative to the false origin.
+    fn forward(&self, ellipsoid: &Ellipsoid, lon: f64, lat: f64) -> (f64, f64) {
+        let a = ellipsoid.semi_major_axis;
+        let e = ellipsoid.eccentricity_squared().sqrt();
+        match *self {
+            Projection::TransverseMercator {
+                latitude_of_origin,
+                longitude_of_origin,
+                scale_factor,
+                ..
+            } => TransverseMercator::new(
+                ellipsoid,
+                latitude_of_origin.to_radians(),
+                longitude_of_origin.to_radians(),
+                scale_factor,
+            )
+            .forward(lon, lat),
+            Projection::Mercator {
+                longitude_of_origin,
+                scale_factor,
+                ..
+            } => (
+                a * scale_factor * (lon - longitude_of_origin.to_radians()),
+                -a * scale_factor * conformal_t(lat, e).ln(),
+            ),
+            Projection::WebMercator {
+                longitude_of_origin,
+                ..
+            } => (
+                a * (lon - longitude_of_origin.to_radians()),
+                a * (FRAC_PI_4 + lat / 2.0).tan().ln(),
+            ),
+            Projection::LambertConformalConic1SP { .. }
+            | Projection::LambertConformalConic2SP { .. } => {
+                LambertConformalConic::new(self, ellipsoid).forward(lon, lat)
+            }
+            Projection::AlbersEqualArea { .. } => {
+                AlbersEqualArea::new(self, ellipsoid).forward(lon, lat)
+            }
+            Projection::ObliqueMercatorA { .. } | Projection::ObliqueMercator { .. } => {
+                ObliqueMercator::new(self, ellipsoid).forward(lon, lat)
+            }
+        }
+    }
+
+    /// The inverse of `forward`.
+    fn inverse(&self, ellipsoid: &Ellipsoid, x: f64, y: f64) -> (f64, f64) {
+        let a = ellipsoid.semi_major_axis;
+        let e = ellipsoid.eccentricity_squared().sqrt();
+        match *self {
+            Projection::TransverseMercator {
+                latitude_of_origin,
+                longitude_of_origin,
+                scale_factor,
+                ..
+            } => TransverseMercator::new(
+                ellipsoid,
+                latitude_of_origin.to_radians(),
+                longitude_of_origin.to_radians(),
+                scale_factor,
+            )
+            .inverse(x, y),
+            Projection::Mercator {
+                longitude_of_origin,
+                scale_factor,
+                ..
+            } => (
+                longitude_of_origin.to_radians() + x / (a * scale_factor),
+                latitude_from_t((-y / (a * scale_factor)).exp(), e),
+            ),
+            Projection::WebMercator {
+                longitude_of_origin,
+                ..
+            } => (
+                longitude_of_origin.to_radians() + x / a,
+                FRAC_PI_2 - 2.0 * (-y / a).exp().atan(),
+            ),
+            Projection::LambertConformalConic1SP { .. }
+            | Projection::LambertConformalConic2SP { .. } => {
+                LambertConformalConic::new(self, ellipsoid).inverse(x, y)
+            }
+            Projection::AlbersEqualArea { .. } => {
+                AlbersEqualArea::new(self, ellipsoid).inverse(x, y)
+            }
+            Projection::ObliqueMercatorA { .. } | Projection::ObliqueMercator { .. } => {
+                ObliqueMercator::new(self, ellipsoid).inverse(x, y)
+            }
+        }
+    }
+}
+
+/// Transverse Mercator using Krüger's series in the third flattening n (up to n^4), which is
+/// accurate to well below a millimetre within a few thousand kilometres of the central meridian.
+struct TransverseMercator {
+    e: f64,
+    /// The rectifying radius times the scale factor.
+    radius: f64,
+    alpha: [f64; 4],
+    beta: [f64; 4],
+    delta: [f64; 4],
+    longitude_of_origin: f64,
+    /// The northing of the latitude of origin on the central meridian.
+    northing_of_origin: f64,
+}
+
+impl TransverseMercator {
+    fn new(
+        ellipsoid: &Ellipsoid,
+        latitude_of_origin: f64,
+        longitude_of_origin: f64,
+        scale_factor: f64,
+    ) -> TransverseMercator {
+        let f = ellipsoid.flattening();
+        let n = f / (2.0 - f);
+        let (n2, n3, n4) = (n * n, n * n * n, n * n * n * n);
+        let mut projection = TransverseMercator {
+            e: ellipsoid.eccentricity_squared().sqrt(),
+            radius: scale_factor * ellipsoid.semi_major_axis / (1.0 + n)
+                * (1.0 + n2 / 4.0 + n4 / 64.0),
+            alpha: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 5.0 * n3 / 16.0 + 41.0 * n4 / 180.0,
+                13.0 * n2 / 48.0 - 3.0 * n3 / 5.0 + 557.0 * n4 / 1440.0,
+                61.0 * n3 / 240.0 - 103.0 * n4 / 140.0,
+                49561.0 * n4 / 161280.0,
+            ],
+            beta: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 37.0 * n3 / 96.0 - n4 / 360.0,
+                n2 / 48.0 + n3 / 15.0 - 437.0 * n4 / 1440.0,
+                17.0 * n3 / 480.0 - 37.0 * n4 / 840.0,
+                4397.0 * n4 / 161280.0,
+            ],
+            delta: [
+                2.0 * n - 2.0 * n2 / 3.0 - 2.0 * n3 + 116.0 * n4 / 45.0,
+                7.0 * n2 / 3.0 - 8.0 * n3 / 5.0 - 227.0 * n4 / 45.0,
+                56.0 * n3 / 15.0 - 136.0 * n4 / 35.0,
+                4279.0 * n4 / 630.0,
+            ],
+            longitude_of_origin,
+            northing_of_origin: 0.0,
+        };
+        projection.northing_of_origin = projection
+            .forward(longitude_of_origin, latitude_of_origin)
+            .1;
+        projection
+    }
+
+    fn forward(&self, lon: f64, lat: f64) -> (f64, f64) {
+        let e = self.e;
+        let t = (lat.sin().atanh() - e * (e * lat.sin()).atanh()).sinh();
+        let dlon = lon - self.longitude_of_origin;
+        let xi = t.atan2(dlon.cos());
+        let eta = (dlon.sin() / (1.0 + t * t).sqrt()).atanh();
+        let (mut x, mut y) = (eta, xi);
+        for (j, alpha) in self.alpha.iter().enumerate() {
+            let k = 2.0 * (j + 1) as f64;
+            x += alpha * (k * xi).cos() * (k * eta).sinh();
+            y += alpha * (k * xi).sin() * (k * eta).cosh();
+        }
+        (self.radius * x, self.radius * y - self.northing_of_origin)
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> (f64, f64) {
+        let xi = (y + self.northing_of_origin) / self.radius;
+        let eta = x / self.radius;
+        let (mut xi_prime, mut eta_prime) = (xi, eta);
+        for (j, beta) in self.beta.iter().enumerate() {
+            let k = 2.0 * (j + 1) as f64;
+            xi_prime -= beta * (k * xi).sin() * (k * eta).cosh();
+            eta_prime -= beta * (k * xi).cos() * (k * eta).sinh();
+        }
+        let chi = (xi_prime.sin() / eta_prime.cosh()).asin();
+        let mut lat = chi;
+        for (j, delta) in self.delta.iter().enumerate() {
+            lat += delta * (2.0 * (j + 1) as f64 * chi).sin();
+        }
+        (
+            self.longitude_of_origin + eta_prime.sinh().atan2(xi_prime.cos()),
+            lat,
+        )
+    }
+}
+
+/// Lambert Conic Conformal, with one or two standard parallels.
+struct LambertConformalConic {
+    e: f64,
+    n: f64,
+    /// a * F * k0 of the EPSG guidance note.
+    af: f64,
+    /// The radius at the latitude of (false) origin.
+    r0: f64,
+    longitude_of_origin: f64,
+}
+
+impl LambertConformalConic {
+    fn new(projection: &Projection, ellipsoid: &Ellipsoid) -> LambertConformalConic {
+        let a = ellipsoid.semi_major_axis;
+        let e2 = ellipsoid.eccentricity_squared();
+        let e = e2.sqrt();
+        let (n, af, origin, longitude_of_origin) = match *projection {
+            Projection::LambertConformalConic1SP {
+                latitude_of_origin,
+                longitude_of_origin,
+                scale_factor,
+                ..
+            } => {
+                let lat0 = latitude_of_origin.to_radians();
+                let n = lat0.sin();
+                let f = m(lat0, e2) / (n * conformal_t(lat0, e).powf(n));
+                (n, a * f * scale_factor, lat0, longitude_of_origin)
+            }
+            Projection::LambertConformalConic2SP {
+                latitude_of_false_origin,
+                longitude_of_false_origin,
+                standard_parallel_1,
+                standard_parallel_2,
+                ..
+            } => {
+                let (lat1, lat2) = (
+                    standard_parallel_1.to_radians(),
+                    standard_parallel_2.to_radians(),
+                );
+                let (m1, t1) = (m(lat1, e2), conformal_t(lat1, e));
+                let n = if (lat1 - lat2).abs() < 1e-12 {
+                    lat1.sin()
+                } else {
+                    (m1.ln() - m(lat2, e2).ln()) / (t1.ln() - conformal_t(lat2, e).ln())
+                };
+                let f = m1 / (n * t1.powf(n));
+                (
+                    n,
+                    a * f,
+                    latitude_of_false_origin.to_radians(),
+                    longitude_of_false_origin,
+                )
+            }
+            _ => unreachable!("Not a Lambert Conic Conformal projection"),
+        };
+        LambertConformalConic {
+            e,
+            n,
+            af,
+            r0: af * conformal_t(origin, e).powf(n),
+            longitude_of_origin: longitude_of_origin.to_radians(),
+        }
+    }
+
+    fn forward(&self, lon: f64, lat: f64) -> (f64, f64) {
+        let r = self.af * conformal_t(lat, self.e).powf(self.n);
+        let theta = self.n * (lon - self.longitude_of_origin);
+        (r * theta.sin(), self.r0 - r * theta.cos())
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> (f64, f64) {
+        let ry = self.r0 - y;
+        let r = self.n.signum() * x.hypot(ry);
+        let theta = if self.n > 0.0 {
+            x.atan2(ry)
+        } else {
+            (-x).atan2(-ry)
+        };
+        let t = (r / self.af).powf(1.0 / self.n);
+        (
+            theta / self.n + self.longitude_of_origin,
+            latitude_from_t(t, self.e),
+        )
+    }
+}
+
+/// Albers Equal Area.
+struct AlbersEqualArea {
+    a: f64,
+    e: f64,
+    n: f64,
+    c: f64,
+    rho0: f64,
+    longitude_of_origin: f64,
+}
+
+impl AlbersEqualArea {
+    fn new(projection: &Projection, ellipsoid: &Ellipsoid) -> AlbersEqualArea {
+        let (origin, longitude_of_origin, lat1, lat2) = match *projection {
+            Projection::AlbersEqualArea {
+                latitude_of_false_origin,
+                longitude_of_false_origin,
+                standard_parallel_1,
+                standard_parallel_2,
+                ..
+            } => (
+                latitude_of_false_origin.to_radians(),
+                longitude_of_false_origin.to_radians(),
+                standard_parallel_1.to_radians(),
+                standard_parallel_2.to_radians(),
+            ),
+            _ => unreachable!("Not an Albers Equal Area projection"),
+        };
+        let a = ellipsoid.semi_major_axis;
+        let e2 = ellipsoid.eccentricity_squared();
+        let e = e2.sqrt();
+        let (m1, m2) = (m(lat1, e2), m(lat2, e2));
+        let (alpha1, alpha2) = (albers_alpha(lat1, e), albers_alpha(lat2, e));
+        let n = if (lat1 - lat2).abs() < 1e-12 {
+            lat1.sin()
+        } else {
+            (m1 * m1 - m2 * m2) / (alpha2 - alpha1)
+        };
+        let c = m1 * m1 + n * alpha1;
+        let mut projection = AlbersEqualArea {
+            a,
+            e,
+            n,
+            c,
+            rho0: 0.0,
+            longitude_of_origin,
+        };
+        projection.rho0 = projection.rho(origin);
+        projection
+    }
+
+    fn rho(&self, lat: f64) -> f64 {
+        self.a
+            * (self.c - self.n * albers_alpha(lat, self.e))
+                .max(0.0)
+                .sqrt()
+            / self.n
+    }
+
+    fn forward(&self, lon: f64, lat: f64) -> (f64, f64) {
+        let rho = self.rho(lat);
+        let theta = self.n * (lon - self.longitude_of_origin);
+        (rho * theta.sin(), self.rho0 - rho * theta.cos())
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> (f64, f64) {
+        let (a, e, n) = (self.a, self.e, self.n);
+        let ry = self.rho0 - y;
+        let rho = x.hypot(ry);
+        let theta = if n > 0.0 {
+            x.atan2(ry)
+        } else {
+            (-x).atan2(-ry)
+        };
+        let alpha = (self.c - rho * rho * n * n / (a * a)) / n;
+        let beta = (alpha / albers_alpha(FRAC_PI_2, e)).clamp(-1.0, 1.0).asin();
+        let (e2, e4, e6) = (e * e, e.powi(4), e.powi(6));
+        let mut lat = beta
+            + (e2 / 3.0 + 31.0 * e4 / 180.0 + 517.0 * e6 / 5040.0) * (2.0 * beta).sin()
+            + (23.0 * e4 / 360.0 + 251.0 * e6 / 3780.0) * (4.0 * beta).sin()
+            + (761.0 * e6 / 45360.0) * (6.0 * beta).sin();
+        // The series is only accurate to a few millimetres, so refine it by Newton's method.
+        for _ in 0..3 {
+            let (sin, cos) = lat.sin_cos();
+            if e < 1e-10 || cos < 1e-10 {
+                break;
+            }
+            let w = 1.0 - e2 * sin * sin;
+            lat += w * w / (2.0 * cos) * (alpha - albers_alpha(lat, e)) / (1.0 - e2);
+        }
+        (self.longitude_of_origin + theta / n, lat)
+    }
+}
+
+/// The alpha (or q) function of the Albers projection.
+fn albers_alpha(lat: f64, e: f64) -> f64 {
+    let sin = lat.sin();
+    if e < 1e-10 {
+        return 2.0 * sin;
+    }
+    let e2 = e * e;
+    (1.0 - e2)
+        * (sin / (1.0 - e2 * sin * sin)
+            - 1.0 / (2.0 * e) * ((1.0 - e * sin) / (1.0 + e * sin)).ln())
+}
+
+/// Hotine Oblique Mercator (variants A and B), following the EPSG guidance note 7-2.
+struct ObliqueMercator {
+    e: f64,
+    a: f64,
+    b: f64,
+    h: f64,
+    gamma0: f64,
+    /// The rectified grid angle.
+    gamma_c: f64,
+    lambda0: f64,
+    /// The u coordinate of the origin, which is the projection centre for variant B and the
+    /// natural origin (0) for variant A.
+    uc: f64,
+}
+
+impl ObliqueMercator {
+    fn new(projection: &Projection, ellipsoid: &Ellipsoid) -> ObliqueMercator {
+        let (lat_c, lon_c, alpha_c, gamma_c, k_c, variant_b) = match *projection {
+            Projection::ObliqueMercatorA {
+                latitude_of_centre,
+                longitude_of_centre,
+                azimuth,
+                rectified_grid_angle,
+                scale_factor,
+                ..
+            } => (
+                latitude_of_centre.to_radians(),
+                longitude_of_centre.to_radians(),
+                azimuth.to_radians(),
+                rectified_grid_angle.to_radians(),
+                scale_factor,
+                false,
+            ),
+            Projection::ObliqueMercator {
+                latitude_of_centre,
+                longitude_of_centre,
+                azimuth,
+                rectified_grid_angle,
+                scale_factor,
+                ..
+            } => (
+                latitude_of_centre.to_radians(),
+                longitude_of_centre.to_radians(),
+                azimuth.to_radians(),
+                rectified_grid_angle.to_radians(),
+                scale_factor,
+                true,
+            ),
+            _ => unreachable!("Not an Oblique Mercator projection"),
+        };
+        let e2 = ellipsoid.eccentricity_squared();
+        let e = e2.sqrt();
+        let sin_c = lat_c.sin();
+        let b = (1.0 + e2 * lat_c.cos().powi(4) / (1.0 - e2)).sqrt();
+        let a =
+            ellipsoid.semi_major_axis * b * k_c * (1.0 - e2).sqrt() / (1.0 - e2 * sin_c * sin_c);
+        let t0 = conformal_t(lat_c, e);
+        let d =
+            (b * (1.0 - e2).sqrt() / (lat_c.cos() * (1.0 - e2 * sin_c * sin_c).sqrt())).max(1.0);
+        let f = d + (d * d - 1.0).sqrt() * lat_c.signum();
+        let h = f * t0.powf(b);
+        let g = (f - 1.0 / f) / 2.0;
+        let gamma0 = (alpha_c.sin() / d).asin();
+        let lambda0 = lon_c - (g * gamma0.tan()).clamp(-1.0, 1.0).asin() / b;
+        let uc = if !variant_b {
+            0.0
+        } else if (alpha_c - FRAC_PI_2).abs() < 1e-12 {
+            a * (lon_c - lambda0)
+        } else {
+            a / b * ((d * d - 1.0).sqrt() / alpha_c.cos()).atan() * lat_c.signum()
+        };
+        ObliqueMercator {
+            e,
+            a,
+            b,
+            h,
+            gamma0,
+            gamma_c,
+            lambda0,
+            uc,
+        }
+    }
+
+    fn forward(&self, lon: f64, lat: f64) -> (f64, f64) {
+        let (a, b) = (self.a, self.b);
+        let q = self.h / conformal_t(lat, self.e).powf(b);
+        let s = (q - 1.0 / q) / 2.0;
+        let t = (q + 1.0 / q) / 2.0;
+        let dlon = b * (lon - self.lambda0);
+        let v = dlon.sin();
+        let u_big = (-v * self.gamma0.cos() + s * self.gamma0.sin()) / t;
+        let v_coordinate = a * ((1.0 - u_big) / (1.0 + u_big)).ln() / (2.0 * b);
+        let u_coordinate =
+            a * (s * self.gamma0.cos() + v * self.gamma0.sin()).atan2(dlon.cos()) / b - self.uc;
+        (
+            v_coordinate * self.gamma_c.cos() + u_coordinate * self.gamma_c.sin(),
+            u_coordinate * self.gamma_c.cos() - v_coordinate * self.gamma_c.sin(),
+        )
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> (f64, f64) {
+        let (a, b, e) = (self.a, self.b, self.e);
+        let v_coordinate = x * self.gamma_c.cos() - y * self.gamma_c.sin();
+        let u_coordinate = y * self.gamma_c.cos() + x * self.gamma_c.sin() + self.uc;
+        let q = (-b * v_coordinate / a).exp();
+        let s = (q - 1.0 / q) / 2.0;
+        let t = (q + 1.0 / q) / 2.0;
+        let v = (b * u_coordinate / a).sin();
+        let u_big = (v * self.gamma0.cos() + s * self.gamma0.sin()) / t;
+        let t_prime = (self.h / ((1.0 + u_big) / (1.0 - u_big)).sqrt()).powf(1.0 / b);
+        (
+            self.lambda0
+                - (s * self.gamma0.cos() - v * self.gamma0.sin())
+                    .atan2((b * u_coordinate / a).cos())
+                    / b,
+            latitude_from_t(t_prime, e),
+        )
+    }
+}
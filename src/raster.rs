@@ -0,0 +1,215 @@
+use sample::{Sample, SampleType};
+
+/// A raster of `width` * `height` pixels with `bands` samples each.
+///
+/// The samples are stored contiguously, row by row, and within a row pixel by pixel with the
+/// samples of a pixel next to each other (i.e., the same layout as a chunky TIFF image).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Raster<T> {
+    width: usize,
+    height: usize,
+    bands: usize,
+    data: Vec<T>,
+}
+
+impl<T: Copy> Raster<T> {
+    /// Creates a raster from its samples, or returns `None` if the number of samples does not
+    /// match the dimensions.
+    pub fn new(width: usize, height: usize, bands: usize, data: Vec<T>) -> Option<Raster<T>> {
+        if width.checked_mul(height)?.checked_mul(bands)? != data.len() {
+            return None;
+        }
+        Some(Raster {
+            width,
+            height,
+            bands,
+            data,
+        })
+    }
+
+    /// Creates a raster where every sample is set to `value`.
+    pub fn filled(width: usize, height: usize, bands: usize, value: T) -> Raster<T> {
+        Raster {
+            width,
+            height,
+            bands,
+            data: vec![value; width * height * bands],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn bands(&self) -> usize {
+        self.bands
+    }
+
+    /// All samples of the raster, in the order described above.
+    pub fn data(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [T] {
+        &mut self.data
+    }
+
+    pub fn into_data(self) -> Vec<T> {
+        self.data
+    }
+
+    /// All samples of row `y`.
+    pub fn row(&self, y: usize) -> Option<&[T]> {
+        let row_len = self.width * self.bands;
+        if y >= self.height {
+            return None;
+        }
+        Some(&self.data[y * row_len..(y + 1) * row_len])
+    }
+
+    /// All samples of the pixel in column `x` and row `y`.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<&[T]> {
+        if x >= self.width {
+            return None;
+        }
+        self.row(y)
+            .map(|row| &row[x * self.bands..(x + 1) * self.bands])
+    }
+
+    /// The sample of `band` of the pixel in column `x` and row `y`.
+    pub fn get(&self, x: usize, y: usize, band: usize) -> Option<T> {
+        if band >= self.bands {
+            return None;
+        }
+        self.pixel(x, y).map(|pixel| pixel[band])
+    }
+
+    /// All samples of a single band, row by row.
+    pub fn band(&self, band: usize) -> Option<Vec<T>> {
+        if band >= self.bands {
+            return None;
+        }
+        Some(
+            self.data
+                .iter()
+                .skip(band)
+                .step_by(self.bands)
+                .copied()
+                .collect(),
+        )
+    }
+}
+
+impl<T: Copy + Into<Sample>> Raster<T> {
+    /// Converts the raster into the nested representation (rows of pixels of samples) that was
+    /// used before rasters were introduced.
+    pub fn to_nested(&self) -> Vec<Vec<Vec<Sample>>> {
+        self.data
+            .chunks_exact((self.width * self.bands).max(1))
+            .map(|row| {
+                row.chunks_exact(self.bands.max(1))
+                    .map(|pixel| pixel.iter().map(|sample| (*sample).into()).collect())
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// A strip or tile viewed directly in the bytes of a TIFF, without copying it.
+///
+/// Like a raster, the samples are stored row by row, pixel by pixel. Tiles at the right and
+/// bottom border of the image are padded, so they may extend beyond the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockView<'a, T> {
+    /// The column of the top left pixel within the image.
+    pub x: usize,
+    /// The row of the top left pixel within the image.
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub bands: usize,
+    pub samples: &'a [T],
+}
+
+/// A raster of any of the supported sample types.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RasterData {
+    U8(Raster<u8>),
+    U16(Raster<u16>),
+    U32(Raster<u32>),
+    U64(Raster<u64>),
+    I8(Raster<i8>),
+    I16(Raster<i16>),
+    I32(Raster<i32>),
+    I64(Raster<i64>),
+    F32(Raster<f32>),
+    F64(Raster<f64>),
+}
+
+/// Evaluates `$body` with `$raster` bound to the typed raster within a `RasterData`.
+macro_rules! with_raster {
+    ($data:expr, $raster:ident => $body:expr) => {
+        match $data {
+            RasterData::U8($raster) => $body,
+            RasterData::U16($raster) => $body,
+            RasterData::U32($raster) => $body,
+            RasterData::U64($raster) => $body,
+            RasterData::I8($raster) => $body,
+            RasterData::I16($raster) => $body,
+            RasterData::I32($raster) => $body,
+            RasterData::I64($raster) => $body,
+            RasterData::F32($raster) => $body,
+            RasterData::F64($raster) => $body,
+        }
+    };
+}
+
+impl RasterData {
+    pub fn width(&self) -> usize {
+        with_raster!(self, raster => raster.width())
+    }
+
+    pub fn height(&self) -> usize {
+        with_raster!(self, raster => raster.height())
+    }
+
+    pub fn bands(&self) -> usize {
+        with_raster!(self, raster => raster.bands())
+    }
+
+    pub fn sample_type(&self) -> SampleType {
+        match *self {
+            RasterData::U8(_) => SampleType::U8,
+            RasterData::U16(_) => SampleType::U16,
+            RasterData::U32(_) => SampleType::U32,
+            RasterData::U64(_) => SampleType::U64,
+            RasterData::I8(_) => SampleType::I8,
+            RasterData::I16(_) => SampleType::I16,
+            RasterData::I32(_) => SampleType::I32,
+            RasterData::I64(_) => SampleType::I64,
+            RasterData::F32(_) => SampleType::F32,
+            RasterData::F64(_) => SampleType::F64,
+        }
+    }
+
+    /// The sample of `band` of the pixel in column `x` and row `y`.
+    pub fn get(&self, x: usize, y: usize, band: usize) -> Option<Sample> {
+        with_raster!(self, raster => raster.get(x, y, band).map(Sample::from))
+    }
+
+    /// All samples of the pixel in column `x` and row `y`.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<Vec<Sample>> {
+        with_raster!(self, raster => raster
+            .pixel(x, y)
+            .map(|pixel| pixel.iter().map(|sample| Sample::from(*sample)).collect()))
+    }
+
+    /// Converts the raster into the nested representation (rows of pixels of samples).
+    pub fn to_nested(&self) -> Vec<Vec<Vec<Sample>>> {
+        with_raster!(self, raster => raster.to_nested())
+    }
+}
@@ -1,19 +1,68 @@
 use num::FromPrimitive;
+use std::collections::HashSet;
 use std::fs::File;
-use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom};
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
 use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
 
 use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt};
-
-use lowlevel::{tag_size, TIFFByteOrder, TIFFTag, TagType};
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+
+#[cfg(feature = "http")]
+use http::HttpReader;
+
+use compression::{decompress, max_decompressed_len};
+use error::{GeoTiffError, Result};
+use gcp::GcpTransformMethod;
+use lowlevel::{tag_size, Compression, Predictor, TIFFByteOrder, TIFFTag, TIFFVariant, TagType};
+use predictor::undo_predictor;
+use raster::{Raster, RasterData};
+use sample::{Sample, SampleType};
 use tiff::{decode_tag, decode_tag_type, IFDEntry, IFD, TIFF};
 
 use crate::lowlevel::TaggedData;
 
+/// The largest buffer that is allocated up front for data whose length is read from the file.
+pub(crate) const MAX_PREALLOCATION: u64 = 1 << 20;
+
+/// A whole `.tiff` file in memory, e.g. a buffer or a memory mapped file, shared between the
+/// reader of its IFDs and direct accesses to its image data.
+#[derive(Clone)]
+pub struct SharedBytes(pub Arc<dyn AsRef<[u8]> + Send + Sync>);
+
+impl AsRef<[u8]> for SharedBytes {
+    fn as_ref(&self) -> &[u8] {
+        (*self.0).as_ref()
+    }
+}
+
 /// A helper trait to indicate that something needs to be seekable and readable.
-pub trait SeekableReader: Seek + Read {}
+pub trait SeekableReader: Seek + Read {
+    /// Announces the byte ranges (offset and length) that are about to be read, e.g. all tiles
+    /// of a window, so that remote sources can fetch them with few requests. Does nothing by
+    /// default.
+    fn prefetch(&mut self, _ranges: &[(u64, u64)]) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Any seekable reader, without prefetching.
+pub struct PlainReader<R>(pub R);
 
-impl<T: Seek + Read> SeekableReader for T {}
+impl<R: Read> Read for PlainReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl<R: Seek> Seek for PlainReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
+
+impl<R: Read + Seek> SeekableReader for PlainReader<R> {}
 
 /// The TIFF reader class that encapsulates all functionality related to reading `.tiff` files.
 /// In particular, this includes reading the TIFF header, the image file directories (IDF), and
@@ -22,18 +71,61 @@ pub struct TIFFReader;
 
 impl TIFFReader {
     /// Loads a `.tiff` file, as specified by `filename`.
+    ///
+    /// Only the header and the IFDs are read, the image data is read on demand.
     pub fn load<T: AsRef<Path>>(&self, path: T) -> Result<Box<TIFF>> {
-        let mut reader = File::open(path)?;
+        let reader = File::open(path)?;
 
-        self.read(&mut reader)
+        self.read(Box::new(PlainReader(reader)))
     }
 
-    /// Reads the `.tiff` file, starting with the byte order.
-    pub fn read(&self, reader: &mut dyn SeekableReader) -> Result<Box<TIFF>> {
-        match self.read_byte_order(reader)? {
-            TIFFByteOrder::LittleEndian => self.read_tiff::<LittleEndian>(reader),
-            TIFFByteOrder::BigEndian => self.read_tiff::<BigEndian>(reader),
-        }
+    /// Memory maps a `.tiff` file, as specified by `filename`.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified while it is mapped, see `memmap2::Mmap::map`.
+    #[cfg(feature = "mmap")]
+    pub unsafe fn load_mmap<T: AsRef<Path>>(&self, path: T) -> Result<Box<TIFF>> {
+        let file = File::open(path)?;
+        let map = Mmap::map(&file)?;
+
+        self.read_bytes(SharedBytes(Arc::new(map)))
+    }
+
+    /// Reads a remote `.tiff` file by HTTP range requests.
+    #[cfg(feature = "http")]
+    pub fn load_url(&self, url: &str) -> Result<Box<TIFF>> {
+        self.read(Box::new(HttpReader::open(url)?))
+    }
+
+    /// Reads a `.tiff` file held in memory. The image data is then accessed in memory as well,
+    /// which allows to view uncompressed strips and tiles without copying them.
+    pub fn read_bytes(&self, bytes: SharedBytes) -> Result<Box<TIFF>> {
+        let mut tiff = self.read(Box::new(PlainReader(Cursor::new(bytes.clone()))))?;
+        tiff.bytes = Some(bytes);
+        Ok(tiff)
+    }
+
+    /// Reads the `.tiff` file, starting with the byte order. The reader is kept to read the image
+    /// data later on.
+    pub fn read(&self, mut reader: Box<dyn SeekableReader + Send>) -> Result<Box<TIFF>> {
+        let byte_order = self.read_byte_order(&mut *reader)?;
+        let ifds = match byte_order {
+            TIFFByteOrder::LittleEndian => self.read_tiff::<LittleEndian>(&mut *reader)?,
+            TIFFByteOrder::BigEndian => self.read_tiff::<BigEndian>(&mut *reader)?,
+        };
+        let images = ifds.iter().map(|_| OnceLock::new()).collect();
+        Ok(Box::new(TIFF {
+            ifds,
+            point_geo_ignore: false,
+            gcp_transform_method: GcpTransformMethod::Polynomial1,
+            byte_order,
+            source: Mutex::new(reader),
+            bytes: None,
+            images,
+            georeference: Mutex::new(None),
+            crs: OnceLock::new(),
+        }))
     }
 
     /// Helper function to read the byte order, one of `LittleEndian` or `BigEndian`.
@@ -43,90 +135,153 @@ impl TIFFReader {
         match TIFFByteOrder::from_u16(reader.read_u16::<LittleEndian>()?) {
             Some(TIFFByteOrder::LittleEndian) => Ok(TIFFByteOrder::LittleEndian),
             Some(TIFFByteOrder::BigEndian) => Ok(TIFFByteOrder::BigEndian),
-            None => Err(Error::new(
-                ErrorKind::Other,
-                "Invalid byte order in header.".to_string(),
+            None => Err(GeoTiffError::InvalidHeader(
+                "Invalid byte order".to_string(),
             )),
         }
     }
 
     /// Reads the `.tiff` file, given a `ByteOrder`.
     ///
-    /// This starts by reading the magic number, the IFD offset, the IFDs themselves, and finally,
-    /// the image data.
-    fn read_tiff<T: ByteOrder>(&self, reader: &mut dyn SeekableReader) -> Result<Box<TIFF>> {
-        self.read_magic::<T>(reader)?;
-        let ifd_offset = self.read_ifd_offset::<T>(reader)?;
-        let ifd = self.read_IFD::<T>(reader, ifd_offset)?;
-        let image_data = self.read_image_data::<T>(reader, &ifd)?;
-        Ok(Box::new(TIFF {
-            ifds: vec![ifd],
-            image_data,
-        }))
+    /// This starts by reading the magic number, the IFD offset, and then the IFDs themselves.
+    fn read_tiff<T: ByteOrder>(&self, reader: &mut dyn SeekableReader) -> Result<Vec<IFD>> {
+        let variant = self.read_magic::<T>(reader)?;
+        let ifd_offset = self.read_ifd_offset::<T>(reader, variant)?;
+        self.read_IFD_chain::<T>(reader, variant, ifd_offset)
     }
 
-    /// Gets the geo_keys if they exist
-
-    /// Reads the magic number, i.e., 42.
-    fn read_magic<T: ByteOrder>(&self, reader: &mut dyn SeekableReader) -> Result<()> {
-        // Bytes 2-3: 0042
+    /// Reads the magic number, i.e., 42 for classic TIFF or 43 for BigTIFF.
+    fn read_magic<T: ByteOrder>(&self, reader: &mut dyn SeekableReader) -> Result<TIFFVariant> {
+        // Bytes 2-3: 0042 or 0043
         // Read and validate HeaderMagic
         match reader.read_u16::<T>()? {
-            42 => Ok(()),
-            _ => Err(Error::new(
-                ErrorKind::Other,
-                "Invalid magic number in header",
-            )),
+            42 => Ok(TIFFVariant::Classic),
+            43 => {
+                // Bytes 4-5: byte size of offsets, always 8.
+                // Bytes 6-7: reserved, always 0.
+                let offset_size = reader.read_u16::<T>()?;
+                let reserved = reader.read_u16::<T>()?;
+                if offset_size != 8 || reserved != 0 {
+                    return Err(GeoTiffError::InvalidHeader(
+                        "Invalid BigTIFF offset size".to_string(),
+                    ));
+                }
+                Ok(TIFFVariant::BigTIFF)
+            }
+            magic => Err(GeoTiffError::InvalidHeader(format!(
+                "Invalid magic number {}",
+                magic
+            ))),
         }
     }
 
     /// Reads the IFD offset. The first IFD is then read from this position.
-    pub fn read_ifd_offset<T: ByteOrder>(&self, reader: &mut dyn SeekableReader) -> Result<u32> {
-        // Bytes 4-7: offset
+    pub fn read_ifd_offset<T: ByteOrder>(
+        &self,
+        reader: &mut dyn SeekableReader,
+        variant: TIFFVariant,
+    ) -> Result<u64> {
+        // Bytes 4-7 (classic) or 8-15 (BigTIFF): offset
         // Offset from start of file to first IFD
-        let ifd_offset_field = reader.read_u32::<T>()?;
-        Ok(ifd_offset_field)
+        self.read_offset::<T>(reader, variant)
+    }
+
+    /// Reads an offset (or any other value stored in an offset sized field), which is 4 bytes
+    /// long in classic TIFF and 8 bytes long in BigTIFF.
+    fn read_offset<T: ByteOrder>(
+        &self,
+        reader: &mut dyn SeekableReader,
+        variant: TIFFVariant,
+    ) -> Result<u64> {
+        match variant {
+            TIFFVariant::Classic => Ok(reader.read_u32::<T>()? as u64),
+            TIFFVariant::BigTIFF => Ok(reader.read_u64::<T>()?),
+        }
+    }
+
+    /// Reads all IFDs, following the linked list of next-IFD offsets starting at `ifd_offset`.
+    ///
+    /// The chain ends at an offset of 0. An offset that was already visited means the chain is
+    /// cyclic, which is reported as an error instead of looping forever.
+    #[allow(non_snake_case)]
+    fn read_IFD_chain<T: ByteOrder>(
+        &self,
+        reader: &mut dyn SeekableReader,
+        variant: TIFFVariant,
+        ifd_offset: u64,
+    ) -> Result<Vec<IFD>> {
+        if ifd_offset == 0 {
+            return Err(GeoTiffError::InvalidIFD("No IFD found.".to_string()));
+        }
+        let mut visited = HashSet::new();
+        let mut ifds = Vec::new();
+        let mut next_offset = ifd_offset;
+        while next_offset != 0 {
+            if !visited.insert(next_offset) {
+                let msg = format!("IFD chain loops back to offset {}.", next_offset);
+                return Err(GeoTiffError::InvalidIFD(msg));
+            }
+            let (ifd, offset) = self.read_IFD::<T>(reader, variant, next_offset)?;
+            ifds.push(ifd);
+            next_offset = offset;
+        }
+        Ok(ifds)
     }
 
-    /// Reads an IFD.
+    /// Reads an IFD, returning it together with the offset of the next IFD (0 if there is none).
     ///
     /// This starts by reading the number of entries, and then the tags within each entry.
     #[allow(non_snake_case)]
     fn read_IFD<T: ByteOrder>(
         &self,
         reader: &mut dyn SeekableReader,
-        ifd_offset: u32,
-    ) -> Result<IFD> {
-        reader.seek(SeekFrom::Start(ifd_offset as u64))?;
-        // 2 byte count of IFD entries
-        let entry_count = reader.read_u16::<T>()?;
+        variant: TIFFVariant,
+        ifd_offset: u64,
+    ) -> Result<(IFD, u64)> {
+        reader.seek(SeekFrom::Start(ifd_offset))?;
+        // 2 byte (classic) or 8 byte (BigTIFF) count of IFD entries
+        let entry_count = match variant {
+            TIFFVariant::Classic => reader.read_u16::<T>()? as u64,
+            TIFFVariant::BigTIFF => reader.read_u64::<T>()?,
+        };
+        let entries_offset = ifd_offset + variant.entry_count_size();
 
         let mut ifd = IFD {
             count: entry_count,
-            entries: Vec::with_capacity(entry_count as usize),
+            entries: Vec::new(),
         };
 
-        for entry_number in 0..entry_count as usize {
-            let entry = self.read_tag::<T>(ifd_offset as u64 + 2, entry_number, reader);
+        for entry_number in 0..entry_count {
+            let entry = self.read_tag::<T>(entries_offset, variant, entry_number, reader);
             match entry {
                 Ok(e) => ifd.entries.push(e),
-                Err(err) => println!("Invalid tag at index {}: {}", entry_number, err),
+                // Private or otherwise unknown tags are skipped, they are not needed to read the
+                // image.
+                Err(GeoTiffError::UnknownTag(_)) | Err(GeoTiffError::UnknownTagType(_)) => {}
+                Err(err) => return Err(err),
             }
         }
 
-        Ok(ifd)
+        // The offset of the next IFD directly follows the entries.
+        reader.seek(SeekFrom::Start(
+            entries_offset + variant.entry_size() * entry_count,
+        ))?;
+        let next_ifd_offset = self.read_offset::<T>(reader, variant)?;
+
+        Ok((ifd, next_ifd_offset))
     }
 
     /// Reads `n` bytes from a reader into a Vec<u8>.
-    fn read_n(&self, reader: &mut dyn SeekableReader, bytes_to_read: u64) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(bytes_to_read as usize);
-        let mut chunk = reader.take(bytes_to_read);
-        let status = chunk.read_to_end(&mut buf);
-        match status {
-            Ok(n) => assert_eq!(bytes_to_read as usize, n),
-            _ => panic!("Didn't read enough"),
+    ///
+    /// The counts come straight from the file, so the buffer is not preallocated beyond a sane
+    /// size: a bogus count then fails with `TruncatedData` instead of exhausting memory.
+    fn read_n(&self, reader: &mut dyn SeekableReader, bytes_to_read: u64) -> Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(bytes_to_read.min(MAX_PREALLOCATION) as usize);
+        reader.take(bytes_to_read).read_to_end(&mut buf)?;
+        if (buf.len() as u64) < bytes_to_read {
+            return Err(GeoTiffError::TruncatedData);
         }
-        buf
+        Ok(buf)
     }
 
     /// Converts a Vec<u8> into a TagValue, depending on the type of the tag. In the TIFF file
@@ -167,20 +322,13 @@ impl TIFFReader {
                 TaggedData::Double(vec.chunks_exact(8).map(Endian::read_f64).collect())
             }
             &TagType::Undefined => TaggedData::Byte(Vec::new()),
-        }
-    }
-
-    /// Converts a number of u8 values to a usize value. This doesn't check if usize is at least
-    /// u64, so be careful with large values.
-    fn vec_to_value<Endian: ByteOrder>(&self, vec: Vec<u8>) -> usize {
-        let len = vec.len();
-        match len {
-            0 => 0_usize,
-            1 => vec[0] as usize,
-            2 => Endian::read_u16(&vec[..]) as usize,
-            4 => Endian::read_u32(&vec[..]) as usize,
-            8 => Endian::read_u64(&vec[..]) as usize,
-            n => panic!("Vector has wrong number of elements, found len={}", n),
+            &TagType::IFD => TaggedData::Long(vec.chunks_exact(4).map(Endian::read_u32).collect()),
+            &TagType::Long8 | &TagType::IFD8 => {
+                TaggedData::Long8(vec.chunks_exact(8).map(Endian::read_u64).collect())
+            }
+            &TagType::SignedLong8 => {
+                TaggedData::SignedLong8(vec.chunks_exact(8).map(Endian::read_i64).collect())
+            }
         }
     }
 
@@ -191,11 +339,13 @@ impl TIFFReader {
     fn read_tag<Endian: ByteOrder>(
         &self,
         ifd_offset: u64,
-        entry_number: usize,
+        variant: TIFFVariant,
+        entry_number: u64,
         reader: &mut dyn SeekableReader,
     ) -> Result<IFDEntry> {
-        // Seek beginning (as each tag is 12 bytes long).
-        reader.seek(SeekFrom::Start(ifd_offset + 12 * entry_number as u64))?;
+        // Seek beginning (as each tag is 12 bytes long, or 20 bytes in BigTIFF).
+        let entry_offset = ifd_offset + variant.entry_size() * entry_number;
+        reader.seek(SeekFrom::Start(entry_offset))?;
 
         // Bytes 0..1: u16 tag ID
         let tag_value = reader.read_u16::<Endian>()?;
@@ -203,37 +353,32 @@ impl TIFFReader {
         // Bytes 2..3: u16 field Type
         let tpe_value = reader.read_u16::<Endian>()?;
 
-        // Bytes 4..7: u32 number of Values of type
-        let count_value = reader.read_u32::<Endian>()?;
+        // Bytes 4..7 (BigTIFF: 4..11): u32 (u64) number of Values of type
+        let count_value = self.read_offset::<Endian>(reader, variant)?;
 
-        // Bytes 8..11: u32 offset in file to Value
-        let value_offset_value = reader.read_u32::<Endian>()?;
+        // Bytes 8..11 (BigTIFF: 12..19): u32 (u64) offset in file to Value
+        let value_offset_value = self.read_offset::<Endian>(reader, variant)?;
 
         // Decode the tag.
-        let tag_msg = format!("Invalid tag {:04X}", tag_value);
-        let tag = decode_tag(tag_value).ok_or(Error::new(ErrorKind::InvalidData, tag_msg))?;
+        let tag = decode_tag(tag_value).ok_or(GeoTiffError::UnknownTag(tag_value))?;
 
         // Decode the type.
-        let tpe_msg = format!("Invalid tag type {:04X}", tpe_value);
-        let tpe = decode_tag_type(tpe_value).expect(&tpe_msg);
-        let value_size = tag_size(&tpe);
+        let tpe = decode_tag_type(tpe_value).ok_or(GeoTiffError::UnknownTagType(tpe_value))?;
+        let value_size = tag_size(&tpe) as u64;
 
         // Let's get the value(s) of this tag.
-        let total_size = count_value * value_size;
-        /*        println!(
-            "{:04X} {:04X} {:08X} {:08X} {:?} {:?} {:?} {:?}",
-            tag_value, tpe_value, count_value, value_offset_value, tag, tpe, value_size, tot_size
-        );*/
-        let number_of_bytes_to_read = (value_size * count_value) as u64;
-        let values: Vec<u8> = if total_size <= 4 {
+        let number_of_bytes_to_read = value_size
+            .checked_mul(count_value)
+            .ok_or(GeoTiffError::TruncatedData)?;
+        let values: Vec<u8> = if number_of_bytes_to_read <= variant.offset_size() {
             // Can directly read the value at the value field. For simplicity, we simply reset
             // the reader to the correct position.
-            reader.seek(SeekFrom::Start(ifd_offset + 12 * entry_number as u64 + 8))?;
-            self.read_n(reader, number_of_bytes_to_read)
+            reader.seek(SeekFrom::Start(entry_offset + 4 + variant.offset_size()))?;
+            self.read_n(reader, number_of_bytes_to_read)?
         } else {
             // Have to read from the address pointed at by the value field.
-            reader.seek(SeekFrom::Start(value_offset_value as u64))?;
-            self.read_n(reader, number_of_bytes_to_read)
+            reader.seek(SeekFrom::Start(value_offset_value))?;
+            self.read_n(reader, number_of_bytes_to_read)?
         };
 
         // Create IFD entry.
@@ -258,232 +403,507 @@ impl TIFFReader {
         Ok(ifd_entry)
     }
 
-    fn get_image_size_data(&self, ifd: &IFD) -> ImageSizeData {
-        // Storage location with  in the TIFF. First, lets get the number of rows per strip.
-        let rows_per_strip = ifd
-            .get(TIFFTag::RowsPerStripTag)
-            // TODO: Should maybe error here if that fails
-            .and_then(|x| x.value.as_unsigned_ints())
-            .and_then(|x| x.first().copied().map(|x| x as u32))
-            .unwrap_or_else(u32::max_value);
-        // For each strip, its offset within the TIFF file.
-        let strip_offsets = ifd
-            .get(TIFFTag::StripOffsetsTag)
-            .and_then(|x| x.value.as_unsigned_ints());
-        let strip_row_byte_counts = ifd
-            .get(TIFFTag::StripByteCountsTag)
-            .and_then(|x| x.value.as_unsigned_ints());
-        let _plainar_configuration = ifd.get(TIFFTag::PlanarConfigurationTag);
-        match strip_offsets.zip(strip_row_byte_counts) {
-            Some((strip_offsets, strip_row_byte_countt)) => ImageSizeData::Image(StripImageData {
-                strip_offsets,
-                strip_row_byte_countt,
-                rows_per_strip,
-            }),
-            _ => {
-                let tile_width = ifd
-                    .get(TIFFTag::TileWidthTag)
-                    .and_then(|x| x.value.as_unsigned_ints())
-                    .and_then(|x| x.first().copied())
-                    .expect("Not enough tile or strip tags found");
-                let tile_length = ifd
-                    .get(TIFFTag::TileHeightTag)
-                    .and_then(|x| x.value.as_unsigned_ints())
-                    .and_then(|x| x.first().copied())
-                    .expect("Not enough tile or strip tags found");
-                let tile_bytes_offsets = ifd
-                    .get(TIFFTag::TileOffsetsTag)
-                    .expect("Not enough tile or strip tags found");
-                let tile_bytes_counts = ifd
-                    .get(TIFFTag::TileByteCountTag)
-                    .expect("Not enough tile or strip tags found");
-                ImageSizeData::Tiles(TiledImageData {
-                    tile_width,
-                    tile_length,
-                    tile_bytes_offsets,
-                    tile_bytes_counts,
-                })
+    /// Determines how the image is split into strips or tiles.
+    ///
+    /// Strips are treated like tiles that span the whole image width, except that the last strip
+    /// may contain less rows than the others.
+    pub fn get_block_layout(&self, ifd: &IFD, width: usize, height: usize) -> Result<BlockLayout> {
+        let mut layout = if ifd.get(TIFFTag::StripOffsetsTag).is_some() {
+            // Storage location within the TIFF. First, lets get the number of rows per strip,
+            // where a missing tag means that the whole image is a single strip.
+            let rows_per_strip = ifd
+                .get_unsigned(TIFFTag::RowsPerStripTag)?
+                .unwrap_or(usize::MAX);
+            if rows_per_strip == 0 {
+                return Err(GeoTiffError::InvalidTagValue(
+                    TIFFTag::RowsPerStripTag,
+                    "Strips must contain at least one row".to_string(),
+                ));
+            }
+            // For each strip, its offset within the TIFF file.
+            BlockLayout {
+                tiled: false,
+                block_count: 0,
+                block_width: width,
+                block_height: rows_per_strip.min(height),
+                blocks_across: 1,
+                offsets: ifd.get_required_unsigned_ints(TIFFTag::StripOffsetsTag)?,
+                byte_counts: ifd.get_required_unsigned_ints(TIFFTag::StripByteCountsTag)?,
+            }
+        } else if ifd.get(TIFFTag::TileOffsetsTag).is_some() {
+            let tile_width = ifd.get_required_unsigned(TIFFTag::TileWidthTag)?;
+            let tile_length = ifd.get_required_unsigned(TIFFTag::TileHeightTag)?;
+            for (tag, value) in [
+                (TIFFTag::TileWidthTag, tile_width),
+                (TIFFTag::TileHeightTag, tile_length),
+            ] {
+                if value == 0 {
+                    return Err(GeoTiffError::InvalidTagValue(
+                        tag,
+                        "Tiles must not be empty".to_string(),
+                    ));
+                }
+            }
+            BlockLayout {
+                tiled: true,
+                block_count: 0,
+                block_width: tile_width,
+                block_height: tile_length,
+                blocks_across: width.div_ceil(tile_width),
+                offsets: ifd.get_required_unsigned_ints(TIFFTag::TileOffsetsTag)?,
+                byte_counts: ifd.get_required_unsigned_ints(TIFFTag::TileByteCountTag)?,
             }
+        } else {
+            return Err(GeoTiffError::MissingTag(TIFFTag::StripOffsetsTag));
+        };
+        // Every block of the image needs an offset and a byte count.
+        let blocks = layout
+            .blocks_across
+            .checked_mul(height.div_ceil(layout.block_height))
+            .ok_or(GeoTiffError::InvalidTagValue(
+                TIFFTag::ImageWidthTag,
+                "Image is too large".to_string(),
+            ))?;
+        if layout.offsets.len() < blocks || layout.byte_counts.len() < blocks {
+            let tag = if layout.tiled {
+                TIFFTag::TileOffsetsTag
+            } else {
+                TIFFTag::StripOffsetsTag
+            };
+            return Err(GeoTiffError::InvalidTagValue(
+                tag,
+                format!(
+                    "Expected {} offsets and byte counts, found {} and {}",
+                    blocks,
+                    layout.offsets.len(),
+                    layout.byte_counts.len()
+                ),
+            ));
         }
+        layout.block_count = blocks;
+        Ok(layout)
     }
 
-    /// Reads the image data into a 3D-Vec<u8>.
+    /// Reads a window of the image described by `ifd`, with the samples of `bands` only.
     ///
-    /// As for now, the following assumptions are made:
-    /// * No compression is used, i.e., CompressionTag == 1.
+    /// Only the strips or tiles that intersect the window are read and decompressed.
+    pub fn read_window(
+        &self,
+        reader: &mut dyn SeekableReader,
+        byte_order: TIFFByteOrder,
+        ifd: &IFD,
+        window: &Window,
+    ) -> Result<RasterData> {
+        match byte_order {
+            TIFFByteOrder::LittleEndian => {
+                self.read_image_data::<LittleEndian>(reader, ifd, window)
+            }
+            TIFFByteOrder::BigEndian => self.read_image_data::<BigEndian>(reader, ifd, window),
+        }
+    }
+
+    /// Reads the image data into a raster, typed according to the sample format.
     fn read_image_data<T: ByteOrder>(
         &self,
         reader: &mut dyn SeekableReader,
         ifd: &IFD,
-    ) -> Result<Vec<Vec<Vec<usize>>>> {
-        let image_size_data = self.get_image_size_data(ifd);
-        match image_size_data {
-            ImageSizeData::Tiles(specifications) => {
-                self.read_tiled_image::<T>(reader, ifd, specifications)
+        window: &Window,
+    ) -> Result<RasterData> {
+        Ok(match ifd.get_sample_type()? {
+            SampleType::U8 => {
+                RasterData::U8(self.read_raster::<T, _>(reader, ifd, window, |b| b[0])?)
             }
-            ImageSizeData::Image(specifications) => {
-                self.read_strip_image::<T>(reader, ifd, specifications)
+            SampleType::U16 => {
+                RasterData::U16(self.read_raster::<T, _>(reader, ifd, window, T::read_u16)?)
             }
-        }
+            SampleType::U32 => {
+                RasterData::U32(self.read_raster::<T, _>(reader, ifd, window, T::read_u32)?)
+            }
+            SampleType::U64 => {
+                RasterData::U64(self.read_raster::<T, _>(reader, ifd, window, T::read_u64)?)
+            }
+            SampleType::I8 => {
+                RasterData::I8(self.read_raster::<T, _>(reader, ifd, window, |b| b[0] as i8)?)
+            }
+            SampleType::I16 => {
+                RasterData::I16(self.read_raster::<T, _>(reader, ifd, window, T::read_i16)?)
+            }
+            SampleType::I32 => {
+                RasterData::I32(self.read_raster::<T, _>(reader, ifd, window, T::read_i32)?)
+            }
+            SampleType::I64 => {
+                RasterData::I64(self.read_raster::<T, _>(reader, ifd, window, T::read_i64)?)
+            }
+            SampleType::F32 => {
+                RasterData::F32(self.read_raster::<T, _>(reader, ifd, window, T::read_f32)?)
+            }
+            SampleType::F64 => {
+                RasterData::F64(self.read_raster::<T, _>(reader, ifd, window, T::read_f64)?)
+            }
+        })
     }
 
-    fn read_strip_image<Endian: ByteOrder>(
+    /// Reads the window into a raster of `S`, using `read_sample` to convert the bytes of a
+    /// single sample.
+    fn read_raster<T: ByteOrder, S: Copy + Default>(
         &self,
         reader: &mut dyn SeekableReader,
         ifd: &IFD,
-        specifications: StripImageData,
-    ) -> Result<Vec<Vec<Vec<usize>>>> {
-        let StripImageData {
-            rows_per_strip: _,
-            strip_offsets,
-            strip_row_byte_countt: strip_row_byte_counts,
-        } = specifications;
-        // Image size and depth.
-        let image_length = ifd.get_image_length()?;
-        let image_width = ifd.get_image_width()?;
-        let image_depth = ifd.get_bytes_per_sample()?;
-        // Create the output Vec.
-
-        // TODO The img Vec should optimally not be of usize, but of size "image_depth".
-        let mut img: Vec<Vec<Vec<usize>>> = Vec::with_capacity(image_length);
-        for i in 0..image_length {
-            img.push(Vec::with_capacity(image_width));
-            for _j in 0..image_width {
-                img[i].push(Vec::with_capacity(image_depth));
+        window: &Window,
+        read_sample: fn(&[u8]) -> S,
+    ) -> Result<Raster<S>> {
+        let image = ImageInfo::new(ifd)?;
+        window.validate(&image)?;
+        let bands = window.bands.len();
+        let layout = self.get_block_layout(ifd, image.width, image.height)?;
+        let depth = image.sample_type.size();
+
+        // Read block after block, and copy the part within the window into the raster. Tiles at
+        // the right and bottom border are padded, these padding pixels are skipped.
+        let first_block_row = window.y / layout.block_height;
+        let last_block_row = (window.y + window.height - 1) / layout.block_height;
+        let first_block_col = window.x / layout.block_width;
+        let last_block_col = (window.x + window.width - 1) / layout.block_width;
+        // Announce all blocks first, so that remote sources can fetch them together. Blocks too
+        // small for their size are rejected before allocating the raster.
+        let mut ranges = Vec::new();
+        for block_row in first_block_row..=last_block_row {
+            for block_col in first_block_col..=last_block_col {
+                let index = block_row * layout.blocks_across + block_col;
+                layout.decoded_len(&image, index)?;
+                ranges.push((
+                    layout.offsets[index] as u64,
+                    layout.byte_counts[index] as u64,
+                ));
             }
         }
-
-        // Read strip after strip, and copy it into the output Vec.
-        let offsets = strip_offsets.clone();
-        let byte_counts = strip_row_byte_counts;
-        // A bit much boilerplate, but should be okay and fast.
-        let mut curr_x = 0;
-        let mut curr_y = 0;
-        let mut curr_z = 0;
-        for (offset, byte_count) in offsets.iter().zip(byte_counts.iter()) {
-            reader.seek(SeekFrom::Start(*offset as u64))?;
-            for _i in 0..(*byte_count / image_depth) {
-                let v = self.read_n(reader, image_depth as u64);
-                img[curr_x][curr_y].push(self.vec_to_value::<Endian>(v));
-                curr_z += 1;
-                if curr_z >= img[curr_x][curr_y].len() {
-                    curr_z = 0;
-                    curr_y += 1;
-                }
-                if curr_y >= img[curr_x].len() {
-                    curr_y = 0;
-                    curr_x += 1;
+        // The window may be as large as the image, whose dimensions come straight from the file,
+        // so guard against absurd sizes.
+        let too_large = || {
+            GeoTiffError::InvalidTagValue(TIFFTag::ImageWidthTag, "Image is too large".to_string())
+        };
+        let len = window
+            .width
+            .checked_mul(window.height)
+            .and_then(|pixels| pixels.checked_mul(bands))
+            .ok_or_else(too_large)?;
+        let mut data = Vec::new();
+        data.try_reserve_exact(len).map_err(|_| too_large())?;
+        data.resize(len, S::default());
+        let mut raster =
+            Raster::new(window.width, window.height, bands, data).ok_or_else(too_large)?;
+        reader.prefetch(&ranges)?;
+        for block_row in first_block_row..=last_block_row {
+            for block_col in first_block_col..=last_block_col {
+                let index = block_row * layout.blocks_across + block_col;
+                let data = self.read_decoded_block::<T>(reader, &image, &layout, index)?;
+                let start_x = block_col * layout.block_width;
+                let start_y = block_row * layout.block_height;
+                let xs = start_x.max(window.x)..(start_x + layout.block_width).min(window.end_x());
+                let ys = start_y.max(window.y)..(start_y + layout.block_height).min(window.end_y());
+                for y in ys {
+                    for x in xs.clone() {
+                        let source = ((y - start_y) * layout.block_width + (x - start_x))
+                            * image.samples_per_pixel;
+                        let target = ((y - window.y) * window.width + (x - window.x)) * bands;
+                        for (value, &band) in raster.data_mut()[target..target + bands]
+                            .iter_mut()
+                            .zip(&window.bands)
+                        {
+                            let offset = (source + band) * depth;
+                            *value = read_sample(&data[offset..offset + depth]);
+                        }
+                    }
                 }
             }
         }
+        Ok(raster)
+    }
 
-        // Return the output Vec.
-        Ok(img)
+    /// Reads the `index`-th strip or tile of an image, decompressed and with the predictor
+    /// reversed, but with its samples still in the byte order of the file.
+    pub fn read_block_data(
+        &self,
+        reader: &mut dyn SeekableReader,
+        byte_order: TIFFByteOrder,
+        image: &ImageInfo,
+        layout: &BlockLayout,
+        index: usize,
+    ) -> Result<Vec<u8>> {
+        match byte_order {
+            TIFFByteOrder::LittleEndian => {
+                self.read_decoded_block::<LittleEndian>(reader, image, layout, index)
+            }
+            TIFFByteOrder::BigEndian => {
+                self.read_decoded_block::<BigEndian>(reader, image, layout, index)
+            }
+        }
     }
 
-    fn read_tiled_image<Endian: ByteOrder>(
+    /// Reads the `index`-th strip or tile, and decompresses it and reverses the predictor.
+    fn read_decoded_block<T: ByteOrder>(
         &self,
         reader: &mut dyn SeekableReader,
-        ifd: &IFD,
-        specifications: TiledImageData,
-    ) -> Result<Vec<Vec<Vec<usize>>>> {
-        let TiledImageData {
-            tile_width,
-            tile_length,
-            tile_bytes_offsets,
-            tile_bytes_counts,
-        } = specifications;
-        // Image size and depth.
-        let image_length = ifd.get_image_length()?;
-        let image_width = ifd.get_image_width()?;
-        let image_depth = ifd.get_bytes_per_sample()?;
-        // Create the output Vec.
-
-        // TODO The img Vec should optimally not be of usize, but of size "image_depth".
-        let mut img: Vec<Vec<Vec<usize>>> = Vec::with_capacity(image_length);
-        for i in 0..image_length {
-            img.push(Vec::with_capacity(image_width));
-            for _j in 0..image_width {
-                img[i].push(Vec::with_capacity(image_depth));
+        image: &ImageInfo,
+        layout: &BlockLayout,
+        index: usize,
+    ) -> Result<Vec<u8>> {
+        let mut data = self.read_block(
+            reader,
+            &image.compression,
+            layout.offsets[index],
+            layout.byte_counts[index],
+            layout.decoded_len(image, index)?,
+        )?;
+        undo_predictor::<T>(
+            &image.predictor,
+            &mut data,
+            layout.block_width,
+            image.samples_per_pixel,
+            image.sample_type.size(),
+        )?;
+        Ok(data)
+    }
+
+    /// Reads a single strip or tile of `byte_count` bytes at `offset`, and decompresses it into
+    /// `decoded_len` bytes.
+    fn read_block(
+        &self,
+        reader: &mut dyn SeekableReader,
+        compression: &Compression,
+        offset: usize,
+        byte_count: usize,
+        decoded_len: usize,
+    ) -> Result<Vec<u8>> {
+        reader.seek(SeekFrom::Start(offset as u64))?;
+        let data = self.read_n(reader, byte_count as u64)?;
+        let data = decompress(compression, data, decoded_len)?;
+        if data.len() < decoded_len {
+            return Err(GeoTiffError::CorruptData(
+                "Strip or tile contains less data than expected.".to_string(),
+            ));
+        }
+        Ok(data)
+    }
+}
+
+/// A rectangular part of an image, together with the bands to read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub bands: Vec<usize>,
+}
+
+impl Window {
+    /// The window covering the whole image, with all bands.
+    pub fn full(ifd: &IFD) -> Result<Window> {
+        let image = ImageInfo::new(ifd)?;
+        Ok(Window {
+            x: 0,
+            y: 0,
+            width: image.width,
+            height: image.height,
+            bands: (0..image.samples_per_pixel).collect(),
+        })
+    }
+
+    fn end_x(&self) -> usize {
+        self.x + self.width
+    }
+
+    fn end_y(&self) -> usize {
+        self.y + self.height
+    }
+
+    /// Checks that the window is not empty and lies within the image.
+    fn validate(&self, image: &ImageInfo) -> Result<()> {
+        if self.width == 0 || self.height == 0 || self.bands.is_empty() {
+            return Err(GeoTiffError::InvalidWindow(
+                "The window must not be empty".to_string(),
+            ));
+        }
+        let inside = self
+            .x
+            .checked_add(self.width)
+            .filter(|&x| x <= image.width)
+            .is_some()
+            && self
+                .y
+                .checked_add(self.height)
+                .filter(|&y| y <= image.height)
+                .is_some();
+        if !inside {
+            return Err(GeoTiffError::InvalidWindow(format!(
+                "The window of {}x{} pixels at ({}, {}) exceeds the image of {}x{} pixels",
+                self.width, self.height, self.x, self.y, image.width, image.height
+            )));
+        }
+        if let Some(band) = self
+            .bands
+            .iter()
+            .find(|&&band| band >= image.samples_per_pixel)
+        {
+            return Err(GeoTiffError::InvalidWindow(format!(
+                "Band {} does not exist, the image has {} bands",
+                band, image.samples_per_pixel
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The properties of an image that are needed to decode its strips or tiles.
+pub struct ImageInfo {
+    pub width: usize,
+    pub height: usize,
+    pub samples_per_pixel: usize,
+    pub sample_type: SampleType,
+    compression: Compression,
+    predictor: Predictor,
+}
+
+impl ImageInfo {
+    pub fn new(ifd: &IFD) -> Result<ImageInfo> {
+        let width = ifd.get_image_width()?;
+        let height = ifd.get_image_length()?;
+        let samples_per_pixel = ifd.get_samples_per_pixel()?;
+        for (tag, value) in [
+            (TIFFTag::ImageWidthTag, width),
+            (TIFFTag::ImageLengthTag, height),
+            (TIFFTag::SamplesPerPixelTag, samples_per_pixel),
+        ] {
+            if value == 0 {
+                return Err(GeoTiffError::InvalidTagValue(
+                    tag,
+                    "Images must not be empty".to_string(),
+                ));
             }
         }
+        // Separate planes store every band in strips or tiles of its own, which is only the same
+        // as storing the bands of a pixel together (chunky) for images with a single band.
+        match ifd.get_unsigned(TIFFTag::PlanarConfigurationTag)? {
+            None | Some(1) => {}
+            Some(2) if samples_per_pixel == 1 => {}
+            Some(value) => return Err(GeoTiffError::UnsupportedPlanarConfiguration(value as u16)),
+        }
+        Ok(ImageInfo {
+            width,
+            height,
+            samples_per_pixel,
+            sample_type: ifd.get_sample_type()?,
+            compression: ifd.get_compression()?,
+            predictor: ifd.get_predictor()?,
+        })
+    }
+}
 
-        // Read tile after tile, and copy it into the output Vec.
-        // Unwrap here, we know it has to be a long or short or if not somethings very wrong
-        // Error handling code would just be removed when we fix how individual tag values are represented
-        let offsets = tile_bytes_offsets
-            .value
-            .as_unsigned_ints()
-            .ok_or(Error::new(ErrorKind::InvalidData, "Couldn't read offsets"))?;
-        let byte_counts = tile_bytes_counts
-            .value
-            .as_unsigned_ints()
-            .ok_or(Error::new(
-                ErrorKind::InvalidData,
-                "Couldn't read byte counts",
-            ))?;
-        // A bit much boilerplate, but should be okay and fast.
-        let mut curr_z = 0;
-        let tiles_across = (image_width + tile_width - 1) / tile_width;
-        let tiles_down = (image_length + tile_length - 1) / tile_length;
-        for (nth_tile, (offset, byte_count)) in offsets.iter().zip(byte_counts.iter()).enumerate() {
-            let tile_col = nth_tile % tiles_across;
-            let tile_row = nth_tile / tiles_across;
-            let start_x = tile_col * tile_width;
-            let mut curr_x = start_x;
-            let end_x = (tile_col + 1) * tile_width;
-            let max_y = tiles_down * tile_length;
-            let start_y = max_y - (tile_row + 1) * tile_length;
-            let mut curr_y = start_y;
-            let _end_y = max_y - tile_row * tile_length;
-            reader.seek(SeekFrom::Start(*offset as u64))?;
-            for _i in 0..(*byte_count / image_depth) {
-                let v = self.read_n(reader, image_depth as u64);
-                if curr_x >= image_width || curr_y >= image_length {
-                    curr_z += 1;
-                    if curr_z >= img[0][0].len() {
-                        curr_z = 0;
-                        curr_x += 1;
-                    }
-                    if curr_x >= end_x {
-                        curr_x = start_x;
-                        curr_y += 1;
-                    }
-                    continue;
-                }
-                img[curr_y][curr_x].push(self.vec_to_value::<Endian>(v));
-                curr_z += 1;
-                if curr_z >= img[curr_y][curr_x].len() {
-                    curr_z = 0;
-                    curr_x += 1;
-                }
-                if curr_x >= end_x {
-                    curr_x = start_x;
-                    curr_y += 1;
+/// How an image is split into strips or tiles (blocks), which are stored left to right, top to
+/// bottom.
+pub struct BlockLayout {
+    tiled: bool,
+    pub block_count: usize,
+    pub block_width: usize,
+    pub block_height: usize,
+    pub blocks_across: usize,
+    offsets: Vec<usize>,
+    byte_counts: Vec<usize>,
+}
+
+impl BlockLayout {
+    /// The number of rows of the `index`-th block. The last strip may contain less rows than the
+    /// others, while tiles are always padded to the full tile size.
+    pub fn rows(&self, image: &ImageInfo, index: usize) -> usize {
+        if self.tiled {
+            self.block_height
+        } else {
+            let first_row = (index / self.blocks_across) * self.block_height;
+            self.block_height.min(image.height - first_row)
+        }
+    }
+
+    /// The number of bytes of the `index`-th block once it is decompressed. Fails if the block is
+    /// too small to hold that many bytes, so no memory is allocated for blocks whose size only
+    /// comes from bogus tags.
+    fn decoded_len(&self, image: &ImageInfo, index: usize) -> Result<usize> {
+        let len = self
+            .rows(image, index)
+            .checked_mul(self.block_width)
+            .and_then(|pixels| pixels.checked_mul(image.samples_per_pixel))
+            .and_then(|samples| samples.checked_mul(image.sample_type.size()))
+            .filter(|&bytes| bytes <= isize::MAX as usize)
+            .ok_or_else(|| {
+                if self.tiled {
+                    GeoTiffError::InvalidTagValue(
+                        TIFFTag::TileWidthTag,
+                        "Tiles are too large".to_string(),
+                    )
+                } else {
+                    GeoTiffError::InvalidTagValue(
+                        TIFFTag::RowsPerStripTag,
+                        "Strips are too large".to_string(),
+                    )
                 }
-            }
+            })?;
+        if len > max_decompressed_len(&image.compression, self.byte_counts[index]) {
+            return Err(GeoTiffError::CorruptData(
+                "Strip or tile contains less data than expected.".to_string(),
+            ));
         }
+        Ok(len)
+    }
 
-        // Return the output Vec.
-        Ok(img)
+    /// The bytes of the `index`-th block within `file`, if they can be used as they are, i.e.,
+    /// if the block is neither compressed nor uses a predictor.
+    pub fn raw_block<'a>(
+        &self,
+        file: &'a [u8],
+        image: &ImageInfo,
+        index: usize,
+    ) -> Result<Option<&'a [u8]>> {
+        if image.compression != Compression::None || image.predictor != Predictor::None {
+            return Ok(None);
+        }
+        let len = self.decoded_len(image, index)?;
+        file.get(self.offsets[index]..)
+            .and_then(|data| data.get(..len))
+            .map(Some)
+            .ok_or(GeoTiffError::TruncatedData)
     }
-}
 
-enum ImageSizeData {
-    Tiles(TiledImageData),
-    Image(StripImageData),
+    /// The index of the block containing pixel (`x`, `y`), together with the position of the
+    /// pixel within the block.
+    pub fn locate(&self, x: usize, y: usize) -> (usize, usize, usize) {
+        let (block_col, block_row) = (x / self.block_width, y / self.block_height);
+        (
+            block_row * self.blocks_across + block_col,
+            x % self.block_width,
+            y % self.block_height,
+        )
+    }
 }
 
-struct StripImageData {
-    strip_offsets: Vec<usize>,
-    strip_row_byte_countt: Vec<usize>,
-    rows_per_strip: u32,
+/// Decodes a single sample stored in the given byte order.
+pub fn decode_sample(byte_order: TIFFByteOrder, sample_type: SampleType, bytes: &[u8]) -> Sample {
+    match byte_order {
+        TIFFByteOrder::LittleEndian => decode_sample_as::<LittleEndian>(sample_type, bytes),
+        TIFFByteOrder::BigEndian => decode_sample_as::<BigEndian>(sample_type, bytes),
+    }
 }
 
-#[derive(Debug)]
-struct TiledImageData {
-    tile_width: usize,
-    tile_length: usize,
-    tile_bytes_counts: IFDEntry,
-    tile_bytes_offsets: IFDEntry,
+fn decode_sample_as<T: ByteOrder>(sample_type: SampleType, bytes: &[u8]) -> Sample {
+    match sample_type {
+        SampleType::U8 => Sample::U8(bytes[0]),
+        SampleType::U16 => Sample::U16(T::read_u16(bytes)),
+        SampleType::U32 => Sample::U32(T::read_u32(bytes)),
+        SampleType::U64 => Sample::U64(T::read_u64(bytes)),
+        SampleType::I8 => Sample::I8(bytes[0] as i8),
+        SampleType::I16 => Sample::I16(T::read_i16(bytes)),
+        SampleType::I32 => Sample::I32(T::read_i32(bytes)),
+        SampleType::I64 => Sample::I64(T::read_i64(bytes)),
+        SampleType::F32 => Sample::F32(T::read_f32(bytes)),
+        SampleType::F64 => Sample::F64(T::read_f64(bytes)),
+    }
 }
@@ -0,0 +1,124 @@
+use lowlevel::SampleFormat;
+
+/// A single sample of an image, typed according to the image's sample format and bit depth.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sample {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+}
+
+impl Sample {
+    /// Converts the sample into a f64, which is lossy for 64 bit integers.
+    pub fn to_f64(&self) -> f64 {
+        match *self {
+            Sample::U8(x) => x as f64,
+            Sample::U16(x) => x as f64,
+            Sample::U32(x) => x as f64,
+            Sample::U64(x) => x as f64,
+            Sample::I8(x) => x as f64,
+            Sample::I16(x) => x as f64,
+            Sample::I32(x) => x as f64,
+            Sample::I64(x) => x as f64,
+            Sample::F32(x) => x as f64,
+            Sample::F64(x) => x,
+        }
+    }
+}
+
+/// The primitive types that samples are stored as, which can be viewed directly in the bytes
+/// of an image.
+///
+/// This trait is sealed: every bit pattern must be a valid value, which only holds for the
+/// primitive integers and floats.
+pub trait SampleValue: Copy + Into<Sample> + sealed::Sealed {
+    const SAMPLE_TYPE: SampleType;
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+macro_rules! impl_sample_value {
+    ($($tpe:ty => $variant:ident),*) => {
+        $(
+            impl From<$tpe> for Sample {
+                fn from(value: $tpe) -> Sample {
+                    Sample::$variant(value)
+                }
+            }
+
+            impl sealed::Sealed for $tpe {}
+
+            impl SampleValue for $tpe {
+                const SAMPLE_TYPE: SampleType = SampleType::$variant;
+            }
+        )*
+    };
+}
+
+impl_sample_value!(
+    u8 => U8, u16 => U16, u32 => U32, u64 => U64,
+    i8 => I8, i16 => I16, i32 => I32, i64 => I64,
+    f32 => F32, f64 => F64
+);
+
+/// The data type of the samples of an image, as given by the SampleFormat and BitsPerSample tags.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleType {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+impl SampleType {
+    /// Determines the sample type, or `None` if the combination of format and size is not
+    /// supported (e.g., 8 bit floats).
+    pub fn new(format: &SampleFormat, bytes_per_sample: usize) -> Option<SampleType> {
+        match (format, bytes_per_sample) {
+            (&SampleFormat::UnsignedInteger, 1) | (&SampleFormat::Undefined, 1) => {
+                Some(SampleType::U8)
+            }
+            (&SampleFormat::UnsignedInteger, 2) | (&SampleFormat::Undefined, 2) => {
+                Some(SampleType::U16)
+            }
+            (&SampleFormat::UnsignedInteger, 4) | (&SampleFormat::Undefined, 4) => {
+                Some(SampleType::U32)
+            }
+            (&SampleFormat::UnsignedInteger, 8) | (&SampleFormat::Undefined, 8) => {
+                Some(SampleType::U64)
+            }
+            (&SampleFormat::TwosComplementSignedInteger, 1) => Some(SampleType::I8),
+            (&SampleFormat::TwosComplementSignedInteger, 2) => Some(SampleType::I16),
+            (&SampleFormat::TwosComplementSignedInteger, 4) => Some(SampleType::I32),
+            (&SampleFormat::TwosComplementSignedInteger, 8) => Some(SampleType::I64),
+            (&SampleFormat::IEEEFloatingPoint, 4) => Some(SampleType::F32),
+            (&SampleFormat::IEEEFloatingPoint, 8) => Some(SampleType::F64),
+            _ => None,
+        }
+    }
+
+    /// The size of a single sample in bytes.
+    pub fn size(&self) -> usize {
+        match *self {
+            SampleType::U8 | SampleType::I8 => 1,
+            SampleType::U16 | SampleType::I16 => 2,
+            SampleType::U32 | SampleType::I32 | SampleType::F32 => 4,
+            SampleType::U64 | SampleType::I64 | SampleType::F64 => 8,
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::PoisonError;
+
+use error::Result;
+use reader::{decode_sample, BlockLayout, ImageInfo, TIFFReader};
+use sample::Sample;
+use tiff::TIFF;
+
+/// Reads single samples of an image without reading the whole image.
+///
+/// Only the strip or tile containing a requested pixel is read. Decoded strips and tiles are
+/// kept in a cache of limited size, where the least recently used ones are dropped first, so
+/// lookups close to each other rarely touch the file. For TIFFs held in memory, strips and
+/// tiles that are neither compressed nor use a predictor are not cached but read in place.
+pub struct Sampler<'a> {
+    tiff: &'a TIFF,
+    image: ImageInfo,
+    layout: BlockLayout,
+    cache: BlockCache,
+}
+
+impl<'a> Sampler<'a> {
+    /// Creates a sampler for the `index`-th image of `tiff`, which keeps up to `cache_size`
+    /// bytes of decoded strips and tiles.
+    pub(crate) fn new(tiff: &'a TIFF, index: usize, cache_size: usize) -> Result<Sampler<'a>> {
+        let ifd = &tiff.ifds[index];
+        let image = ImageInfo::new(ifd)?;
+        let layout = TIFFReader.get_block_layout(ifd, image.width, image.height)?;
+        Ok(Sampler {
+            tiff,
+            image,
+            layout,
+            cache: BlockCache::new(cache_size),
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.image.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.image.height
+    }
+
+    pub fn bands(&self) -> usize {
+        self.image.samples_per_pixel
+    }
+
+    /// Gets the sample of `band` at pixel (`x`, `y`), or `None` if there is no such sample.
+    pub fn get(&mut self, x: usize, y: usize, band: usize) -> Result<Option<Sample>> {
+        if x >= self.image.width || y >= self.image.height || band >= self.image.samples_per_pixel {
+            return Ok(None);
+        }
+        let (index, block_x, block_y) = self.layout.locate(x, y);
+        let size = self.image.sample_type.size();
+        let offset = ((block_y * self.layout.block_width + block_x) * self.image.samples_per_pixel
+            + band)
+            * size;
+        // Blocks that need no decoding are read directly from memory, without caching them.
+        if let Some(ref bytes) = self.tiff.bytes {
+            if let Some(data) = self.layout.raw_block(bytes.as_ref(), &self.image, index)? {
+                return Ok(Some(decode_sample(
+                    self.tiff.byte_order,
+                    self.image.sample_type,
+                    &data[offset..offset + size],
+                )));
+            }
+        }
+        if !self.cache.contains(index) {
+            let data = {
+                let mut source = self
+                    .tiff
+                    .source
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                TIFFReader.read_block_data(
+                    &mut **source,
+                    self.tiff.byte_order,
+                    &self.image,
+                    &self.layout,
+                    index,
+                )?
+            };
+            self.cache.insert(index, data);
+        }
+        let data = self.cache.get(index).expect("Block was just cached");
+        Ok(Some(decode_sample(
+            self.tiff.byte_order,
+            self.image.sample_type,
+            &data[offset..offset + size],
+        )))
+    }
+}
+
+/// Decoded blocks, by their index, of at most `capacity` bytes in total (unless a single block
+/// is larger). When full, the least recently used blocks are dropped.
+struct BlockCache {
+    capacity: usize,
+    size: usize,
+    /// Increases with every access, so that older accesses have smaller ticks.
+    tick: u64,
+    blocks: HashMap<usize, (u64, Vec<u8>)>,
+    /// The blocks by the tick of their last access.
+    recency: BTreeMap<u64, usize>,
+}
+
+impl BlockCache {
+    fn new(capacity: usize) -> BlockCache {
+        BlockCache {
+            capacity,
+            size: 0,
+            tick: 0,
+            blocks: HashMap::new(),
+            recency: BTreeMap::new(),
+        }
+    }
+
+    fn contains(&self, index: usize) -> bool {
+        self.blocks.contains_key(&index)
+    }
+
+    fn get(&mut self, index: usize) -> Option<&[u8]> {
+        let (tick, data) = self.blocks.get_mut(&index)?;
+        self.tick += 1;
+        self.recency.remove(tick);
+        self.recency.insert(self.tick, index);
+        *tick = self.tick;
+        Some(data)
+    }
+
+    fn insert(&mut self, index: usize, data: Vec<u8>) {
+        while self.size + data.len() > self.capacity {
+            let oldest = match self.recency.pop_first() {
+                Some((_, oldest)) => oldest,
+                None => break,
+            };
+            if let Some((_, block)) = self.blocks.remove(&oldest) {
+                self.size -= block.len();
+            }
+        }
+        self.tick += 1;
+        self.size += data.len();
+        self.recency.insert(self.tick, index);
+        if let Some((tick, block)) = self.blocks.insert(index, (self.tick, data)) {
+            self.recency.remove(&tick);
+            self.size -= block.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BlockCache;
+
+    /// Checks that the cache holds exactly `indices`, and that its size and recency match them.
+    fn assert_cached(cache: &BlockCache, indices: &[usize]) {
+        let mut cached: Vec<_> = cache.blocks.keys().copied().collect();
+        cached.sort_unstable();
+        assert_eq!(cached, indices);
+        let size: usize = cache.blocks.values().map(|(_, block)| block.len()).sum();
+        assert_eq!(cache.size, size);
+        assert_eq!(cache.recency.len(), indices.len());
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = BlockCache::new(30);
+        for index in 0..3 {
+            cache.insert(index, vec![index as u8; 10]);
+        }
+        assert_cached(&cache, &[0, 1, 2]);
+        cache.insert(3, vec![3; 10]);
+        assert_cached(&cache, &[1, 2, 3]);
+
+        // Getting a block makes it the most recently used one.
+        assert_eq!(cache.get(1), Some(&[1; 10][..]));
+        cache.insert(4, vec![4; 10]);
+        assert_cached(&cache, &[1, 3, 4]);
+        assert_eq!(cache.get(2), None);
+    }
+
+    #[test]
+    fn test_size_bound() {
+        let mut cache = BlockCache::new(25);
+        for index in 0..3 {
+            cache.insert(index, vec![0; 10]);
+        }
+        assert_cached(&cache, &[1, 2]);
+        // A single block larger than the cache replaces all others.
+        cache.insert(3, vec![0; 40]);
+        assert_cached(&cache, &[3]);
+        cache.insert(4, vec![0; 5]);
+        assert_cached(&cache, &[4]);
+
+        let mut cache = BlockCache::new(0);
+        cache.insert(0, vec![0; 10]);
+        cache.insert(1, vec![0; 10]);
+        assert_cached(&cache, &[1]);
+    }
+
+    #[test]
+    fn test_reinsert() {
+        let mut cache = BlockCache::new(30);
+        cache.insert(0, vec![0; 10]);
+        cache.insert(1, vec![1; 10]);
+        cache.insert(0, vec![2; 5]);
+        assert_cached(&cache, &[0, 1]);
+        assert_eq!(cache.get(0), Some(&[2; 5][..]));
+
+        // Re-inserting block 0 made block 1 the least recently used one.
+        cache.insert(2, vec![0; 10]);
+        cache.insert(3, vec![0; 10]);
+        assert_cached(&cache, &[0, 2, 3]);
+    }
+}
@@ -1,17 +1,52 @@
+use crs::Crs;
 use enum_primitive::FromPrimitive;
+use error::{GeoTiffError, Result};
+use gcp::GcpTransformMethod;
+use geotransform::{Georeference, RasterType};
 use lowlevel::*;
+use raster::RasterData;
+use reader::{SeekableReader, SharedBytes};
+use sample::SampleType;
 use std::collections::HashSet;
-use std::io::{Error, ErrorKind, Result};
+use std::fmt;
+use std::sync::{Arc, Mutex, OnceLock};
 
 /// The basic TIFF struct. This includes the header (specifying byte order and IFD offsets) as
-/// well as all the image file directories (IFDs) plus image data.
+/// well as all the image file directories (IFDs).
 ///
-/// The image data has a size of width * length * bytes_per_sample.
-#[derive(Debug)]
+/// Opening a file only reads its header and IFDs. The image data of every IFD (the first image,
+/// and e.g. additional pages or overviews) is read from the file on demand, either as a whole or
+/// as a window.
 pub struct TIFF {
     pub ifds: Vec<IFD>,
-    // This is width * length * bytes_per_sample.
-    pub image_data: Vec<Vec<Vec<usize>>>,
+    /// Whether to ignore the raster type of PixelIsPoint images when georeferencing them, like
+    /// GDAL's `GTIFF_POINT_GEO_IGNORE` option does. Defaults to `false`.
+    pub point_geo_ignore: bool,
+    /// How to fit the transform of images that are georeferenced by ground control points.
+    /// Defaults to a first order polynomial.
+    pub gcp_transform_method: GcpTransformMethod,
+    pub(crate) byte_order: TIFFByteOrder,
+    /// The file, which the image data is read from.
+    pub(crate) source: Mutex<Box<dyn SeekableReader + Send>>,
+    /// The whole file, if it is held in memory (e.g., memory mapped).
+    pub(crate) bytes: Option<SharedBytes>,
+    /// The image data of every IFD, once it has been read as a whole.
+    pub(crate) images: Vec<OnceLock<RasterData>>,
+    /// The georeference of the first image, once it has been used.
+    pub(crate) georeference: Mutex<Option<Arc<Georeference>>>,
+    /// The CRS of the first image once it has been used, or `None` if it can't be resolved.
+    pub(crate) crs: OnceLock<Option<Crs>>,
+}
+
+impl fmt::Debug for TIFF {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TIFF")
+            .field("ifds", &self.ifds)
+            .field("point_geo_ignore", &self.point_geo_ignore)
+            .field("gcp_transform_method", &self.gcp_transform_method)
+            .field("byte_order", &self.byte_order)
+            .finish_non_exhaustive()
+    }
 }
 
 /// The header of a TIFF file. This comes first in any TIFF file and contains the byte order
@@ -19,44 +54,176 @@ pub struct TIFF {
 #[derive(Debug)]
 pub struct TIFFHeader {
     pub byte_order: TIFFByteOrder,
-    pub ifd_offset: Long,
+    pub variant: TIFFVariant,
+    pub ifd_offset: Long8,
 }
 
 /// An image file directory (IFD) within this TIFF. It contains the number of individual IFD entries
 /// as well as a Vec with all the entries.
 #[derive(Debug)]
 pub struct IFD {
-    pub count: u16,
+    pub count: u64,
     pub entries: Vec<IFDEntry>,
 }
 
 impl IFD {
+    /// Reads the GeoKeys of the GeoKey directory.
     pub fn get_geo_keys(&self) -> Result<Vec<GeoKey>> {
-        self.entries
-            .iter()
-            .find(|&e| e.tag == TIFFTag::GeoKeyDirectoryTag).and_then(|x|x.value.as_shorts())
-            .map(|values| {
-                let mut values=values.iter().cloned().array_chunks::<4>();
-                // If this unwrap fails then somethings very wrong
-                let directory_header=values.next().unwrap();
-                let _directory_version = directory_header[0];
-                let _revision = directory_header[1];
-                let _minor_revision = directory_header[2];
-                let number_of_keys = directory_header[3] as usize;
-			                let tags= values.clone().take(number_of_keys);
-				let _shorts_array:Vec<_>=values.skip(number_of_keys).flatten().collect();
-                tags.filter_map(|[id, location, count, value]| {
-                        // Assume no extra values are needed for now, aka location=0 and count =1
-                        if location!= 0 && count != 1 {
-                            eprintln!("Cannot yet handle geotiffs with non-integer valued keys, id={}, location={}, count={}",id, location, count);
-                            return None;
-                        };
-                        Some(GeoKey::new(id,value))
-                    })
-                    .collect::<Vec<_>>()
+        self.get_geo_key_directory().map(|directory| directory.keys)
+    }
+
+    /// Reads the GeoKey directory, i.e., its header and all of its keys.
+    ///
+    /// Only directories of GeoTIFF 1.0 (revision 1.0) and GeoTIFF 1.1 (revision 1.1) are
+    /// supported, as later revisions may change the meaning of the keys.
+    ///
+    /// Short values are either stored within the key entry itself or, like longer lists of
+    /// shorts, at the end of the directory. Doubles and strings are stored in the
+    /// GeoDoubleParams and GeoAsciiParams tags, where the strings are terminated by a pipe.
+    pub fn get_geo_key_directory(&self) -> Result<GeoKeyDirectory> {
+        let entry = self
+            .get(TIFFTag::GeoKeyDirectoryTag)
+            .ok_or(GeoTiffError::MissingTag(TIFFTag::GeoKeyDirectoryTag))?;
+        let shorts = entry
+            .value
+            .as_shorts()
+            .ok_or(GeoTiffError::InvalidTagType(entry.tag, entry.tpe))?;
+        if shorts.len() < 4 {
+            return Err(invalid_geo_keys("Missing header".to_string()));
+        }
+        let header = GeoKeyDirectoryInfo {
+            directory_version: shorts[0],
+            revision: shorts[1],
+            minor_revision: shorts[2],
+            number_of_keys: shorts[3],
+        };
+        let version = header
+            .version()
+            .ok_or(GeoTiffError::UnsupportedGeoKeyVersion(
+                header.directory_version,
+                header.revision,
+                header.minor_revision,
+            ))?;
+        let number_of_keys = header.number_of_keys as usize;
+        let keys = shorts[4..]
+            .chunks_exact(4)
+            .take(number_of_keys)
+            .collect::<Vec<_>>();
+        if keys.len() < number_of_keys {
+            return Err(invalid_geo_keys(format!(
+                "Expected {} keys, found {}",
+                number_of_keys,
+                keys.len()
+            )));
+        }
+
+        let keys = keys
+            .into_iter()
+            .map(|key| {
+                let (id, location, count, value) = (key[0], key[1], key[2] as usize, key[3]);
+                let value = if location == 0 {
+                    GeoKeyValue::Short(value)
+                } else {
+                    self.get_geo_key_params(&shorts, id, location, value as usize, count)?
+                };
+                Ok(GeoKey::new(id, value))
             })
-            .ok_or(Error::new(ErrorKind::InvalidData, "geo key directory not found."))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(GeoKeyDirectory {
+            header,
+            version,
+            keys,
+        })
     }
+
+    /// Gets the raster type given by the GeoKeys, which defaults to PixelIsArea if there are no
+    /// GeoKeys or the raster type key is missing.
+    ///
+    /// Like GDAL, only the raster type key is looked up, so errors elsewhere in the GeoKey
+    /// directory (e.g., an unsupported version) don't prevent georeferencing the image by its
+    /// tags. A broken directory is treated as if it was missing.
+    pub fn get_raster_type(&self) -> RasterType {
+        let shorts = match self
+            .get(TIFFTag::GeoKeyDirectoryTag)
+            .and_then(|entry| entry.value.as_shorts())
+        {
+            Some(shorts) => shorts,
+            None => return RasterType::PixelIsArea,
+        };
+        let number_of_keys = shorts.get(3).map_or(0, |&n| n as usize);
+        shorts
+            .get(4..)
+            .unwrap_or_default()
+            .chunks_exact(4)
+            .take(number_of_keys)
+            // The raster type is a single short stored within the key entry.
+            .find(|key| key[0] == 1025 && key[1] == 0)
+            .map_or(RasterType::PixelIsArea, |key| RasterType::new(key[3]))
+    }
+
+    /// Reads the `count` values starting at `offset` of the tag with ID `location`.
+    fn get_geo_key_params(
+        &self,
+        directory: &[u16],
+        id: u16,
+        location: u16,
+        offset: usize,
+        count: usize,
+    ) -> Result<GeoKeyValue> {
+        let out_of_bounds = || {
+            invalid_geo_keys(format!(
+                "Values of key {} are outside of tag {}",
+                id, location
+            ))
+        };
+        let tag = decode_tag(location).ok_or(GeoTiffError::UnknownTag(location))?;
+        if tag == TIFFTag::GeoKeyDirectoryTag {
+            let values = directory
+                .get(offset..offset + count)
+                .ok_or_else(out_of_bounds)?;
+            return Ok(match *values {
+                [value] => GeoKeyValue::Short(value),
+                _ => GeoKeyValue::Shorts(values.to_vec()),
+            });
+        }
+
+        let entry = self.get(tag).ok_or(GeoTiffError::MissingTag(tag))?;
+        match tag {
+            TIFFTag::GeoDoubleParamsTag => {
+                let doubles = entry
+                    .value
+                    .as_doubles()
+                    .ok_or(GeoTiffError::InvalidTagType(tag, entry.tpe))?;
+                let values = doubles
+                    .get(offset..offset + count)
+                    .ok_or_else(out_of_bounds)?;
+                Ok(GeoKeyValue::Double(values.to_vec()))
+            }
+            TIFFTag::GeoAsciiParamsTag => {
+                let ascii = entry
+                    .value
+                    .as_ascii()
+                    .ok_or(GeoTiffError::InvalidTagType(tag, entry.tpe))?;
+                let value = ascii
+                    .as_bytes()
+                    .get(offset..offset + count)
+                    .ok_or_else(out_of_bounds)?;
+                // Strings end with a pipe, which replaces the NUL terminator of TIFF strings.
+                let value = String::from_utf8_lossy(value);
+                Ok(GeoKeyValue::Ascii(
+                    value.trim_end_matches(['|', '\0']).to_string(),
+                ))
+            }
+            _ => Err(GeoTiffError::InvalidTagValue(
+                TIFFTag::GeoKeyDirectoryTag,
+                format!("Values of key {} can't be stored in tag {:?}", id, tag),
+            )),
+        }
+    }
+}
+
+fn invalid_geo_keys(msg: String) -> GeoTiffError {
+    GeoTiffError::InvalidTagValue(TIFFTag::GeoKeyDirectoryTag, msg)
 }
 
 /// A single entry within an image file directory (IDF). It consists of a tag, a type, and several
@@ -65,8 +232,8 @@ impl IFD {
 pub struct IFDEntry {
     pub tag: TIFFTag,
     pub tpe: TagType,
-    pub count: Long,
-    pub value_offset: Long,
+    pub count: Long8,
+    pub value_offset: Long8,
     pub value: TaggedData,
 }
 
@@ -76,40 +243,119 @@ impl IFD {
         self.entries.iter().find(|&e| e.tag == tag).cloned()
     }
 
-    pub fn get_image_length(&self) -> Result<usize> {
-        self.entries
+    /// Gets the first value of a tag holding unsigned integers, or `None` if the tag is missing.
+    pub fn get_unsigned(&self, tag: TIFFTag) -> Result<Option<usize>> {
+        match self.entries.iter().find(|&e| e.tag == tag) {
+            Some(entry) => entry
+                .value
+                .as_unsigned_ints()
+                .and_then(|x| x.first().copied())
+                .map(Some)
+                .ok_or(GeoTiffError::InvalidTagType(tag, entry.tpe)),
+            None => Ok(None),
+        }
+    }
+
+    /// Gets the first value of a tag holding unsigned integers, which is required to exist.
+    pub fn get_required_unsigned(&self, tag: TIFFTag) -> Result<usize> {
+        self.get_unsigned(tag)?.ok_or(GeoTiffError::MissingTag(tag))
+    }
+
+    /// Gets all values of a tag holding unsigned integers, which is required to exist.
+    pub fn get_required_unsigned_ints(&self, tag: TIFFTag) -> Result<Vec<usize>> {
+        let entry = self
+            .entries
             .iter()
-            .find(|&e| e.tag == TIFFTag::ImageLengthTag)
-            .and_then(|x| x.value.as_unsigned_ints())
-            .and_then(|x| x.first().copied())
-            .ok_or(Error::new(
-                ErrorKind::InvalidData,
-                "Image length not found.",
-            ))
+            .find(|&e| e.tag == tag)
+            .ok_or(GeoTiffError::MissingTag(tag))?;
+        entry
+            .value
+            .as_unsigned_ints()
+            .ok_or(GeoTiffError::InvalidTagType(tag, entry.tpe))
+    }
+
+    pub fn get_image_length(&self) -> Result<usize> {
+        self.get_required_unsigned(TIFFTag::ImageLengthTag)
     }
 
     pub fn get_image_width(&self) -> Result<usize> {
-        self.entries
-            .iter()
-            .find(|&e| e.tag == TIFFTag::ImageWidthTag)
-            .and_then(|tag| tag.value.as_unsigned_ints())
-            .and_then(|x| x.first().copied())
-            .ok_or(Error::new(ErrorKind::InvalidData, "Image width not found."))
+        self.get_required_unsigned(TIFFTag::ImageWidthTag)
     }
 
     pub fn get_bytes_per_sample(&self) -> Result<usize> {
-        self.entries
-            .iter()
-            .find(|&e| e.tag == TIFFTag::BitsPerSampleTag)
-            .and_then(|tag| tag.value.as_unsigned_ints())
-            .and_then(|x| x.first().copied())
-            // This gets bits, so need to turn into bytes
+        // This gets bits, so need to turn into bytes
+        self.get_required_unsigned(TIFFTag::BitsPerSampleTag)
             .map(|x| x / 8)
-            .ok_or(Error::new(ErrorKind::InvalidData, "Image depth not found."))
     }
+
+    /// Gets the number of samples per pixel, which defaults to 1 if the tag is missing.
+    pub fn get_samples_per_pixel(&self) -> Result<usize> {
+        Ok(self.get_unsigned(TIFFTag::SamplesPerPixelTag)?.unwrap_or(1))
+    }
+
+    /// Gets the sample format, which defaults to unsigned integers if the tag is missing. All
+    /// samples of a pixel are required to share the same format.
+    pub fn get_sample_format(&self) -> Result<SampleFormat> {
+        match self.get_unsigned(TIFFTag::SampleFormatTag)? {
+            Some(value) => SampleFormat::from_usize(value).ok_or(
+                GeoTiffError::UnsupportedSampleFormat(value as u16, self.get_bits_per_sample()?),
+            ),
+            None => Ok(SampleFormat::UnsignedInteger),
+        }
+    }
+
+    /// Gets the data type of the samples, combining the sample format and the bits per sample.
+    pub fn get_sample_type(&self) -> Result<SampleType> {
+        let sample_format = self.get_sample_format()?;
+        let bits_per_sample = self.get_bits_per_sample()?;
+        if bits_per_sample % 8 != 0 {
+            return Err(GeoTiffError::UnsupportedSampleFormat(
+                sample_format as u16,
+                bits_per_sample,
+            ));
+        }
+        SampleType::new(&sample_format, bits_per_sample / 8).ok_or(
+            GeoTiffError::UnsupportedSampleFormat(sample_format as u16, bits_per_sample),
+        )
+    }
+
+    fn get_bits_per_sample(&self) -> Result<usize> {
+        self.get_required_unsigned(TIFFTag::BitsPerSampleTag)
+    }
+
+    /// Gets the compression of the image data, which defaults to no compression if the tag is
+    /// missing.
+    pub fn get_compression(&self) -> Result<Compression> {
+        match self.get_unsigned(TIFFTag::CompressionTag)? {
+            Some(value) => Compression::from_usize(value)
+                .ok_or(GeoTiffError::UnsupportedCompression(value as u16)),
+            None => Ok(Compression::None),
+        }
+    }
+
+    /// Gets the predictor applied to the image data, which defaults to no predictor if the tag is
+    /// missing.
+    pub fn get_predictor(&self) -> Result<Predictor> {
+        match self.get_unsigned(TIFFTag::PredictorTag)? {
+            Some(value) => {
+                Predictor::from_usize(value).ok_or(GeoTiffError::UnsupportedPredictor(value as u16))
+            }
+            None => Ok(Predictor::None),
+        }
+    }
+}
+
+/// The version of the GeoTIFF specification a GeoKey directory follows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GeoTiffVersion {
+    /// GeoTIFF 1.0, i.e., key revision 1.0.
+    V1_0,
+    /// GeoTIFF 1.1 (OGC 19-008r4), i.e., key revision 1.1.
+    V1_1,
 }
 
-#[derive(Clone, Debug)]
+/// The header of a GeoKey directory.
+#[derive(Clone, Debug, PartialEq)]
 pub struct GeoKeyDirectoryInfo {
     pub directory_version: u16,
     pub revision: u16,
@@ -117,6 +363,26 @@ pub struct GeoKeyDirectoryInfo {
     pub number_of_keys: u16,
 }
 
+impl GeoKeyDirectoryInfo {
+    /// The GeoTIFF version, or `None` if the header describes an unsupported version.
+    pub fn version(&self) -> Option<GeoTiffVersion> {
+        match (self.directory_version, self.revision, self.minor_revision) {
+            (1, 1, 0) => Some(GeoTiffVersion::V1_0),
+            (1, 1, 1) => Some(GeoTiffVersion::V1_1),
+            _ => None,
+        }
+    }
+}
+
+/// A GeoKey directory, consisting of its header and the keys it holds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeoKeyDirectory {
+    pub header: GeoKeyDirectoryInfo,
+    /// The GeoTIFF version given by the header.
+    pub version: GeoTiffVersion,
+    pub keys: Vec<GeoKey>,
+}
+
 /// Decodes an u16 value into a TIFFTag.
 pub fn decode_tag(value: u16) -> Option<TIFFTag> {
     TIFFTag::from_u16(value)
@@ -179,45 +445,168 @@ pub fn validate_required_tags_for(typ: &ImageType) -> Option<HashSet<TIFFTag>> {
     }
 }
 
-#[derive(Debug)]
+/// The value of a GeoKey, typed according to the tag it is stored in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoKeyValue {
+    Short(u16),
+    Shorts(Vec<u16>),
+    Double(Vec<f64>),
+    Ascii(String),
+}
+
+impl GeoKeyValue {
+    /// The value if it is a single short.
+    pub fn as_short(&self) -> Option<u16> {
+        match *self {
+            GeoKeyValue::Short(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// The value if it is a string.
+    pub fn as_ascii(&self) -> Option<String> {
+        match *self {
+            GeoKeyValue::Ascii(ref x) => Some(x.clone()),
+            _ => None,
+        }
+    }
+
+    /// The value if it is a list of doubles.
+    pub fn as_doubles(&self) -> Option<Vec<f64>> {
+        match *self {
+            GeoKeyValue::Double(ref x) => Some(x.clone()),
+            _ => None,
+        }
+    }
+
+    /// The value if it is a single double.
+    pub fn as_double(&self) -> Option<f64> {
+        match *self {
+            GeoKeyValue::Double(ref x) if x.len() == 1 => Some(x[0]),
+            _ => None,
+        }
+    }
+}
+
+/// A GeoKey of the GeoTIFF 1.1 specification, with its value.
+///
+/// The variants use the key names of GeoTIFF 1.0, which are still the ones most tools use.
+#[derive(Debug, Clone, PartialEq)]
 pub enum GeoKey {
+    // Configuration keys.
     GTModelTypeGeoKey(u16),
     GTRasterTypeGeoKey(u16),
+    GTCitationGeoKey(String),
+
+    // Geodetic CRS parameter keys.
     GeographicTypeGeoKey(u16),
+    GeogCitationGeoKey(String),
     GeogGeodeticDatumGeoKey(u16),
     GeogPrimeMeridianGeoKey(u16),
     GeogLinearUnitsGeoKey(u16),
-    GeogLinearUnitSizeGeoKey(u16),
+    GeogLinearUnitSizeGeoKey(f64),
     GeogAngularUnitsGeoKey(u16),
-    GeogAngularUnitSizeGeoKey(u16),
+    GeogAngularUnitSizeGeoKey(f64),
     GeogEllipsoidGeoKey(u16),
-    GeogSemiMajorAxisGeoKey(u16),
-    GeogSemiMinorAxisGeoKey(u16),
-    GeogInvFlatteningGeoKey(u16),
+    GeogSemiMajorAxisGeoKey(f64),
+    GeogSemiMinorAxisGeoKey(f64),
+    GeogInvFlatteningGeoKey(f64),
     GeogAzimuthUnitsGeoKey(u16),
-    GeogPrimeMeridianLongGeoKey(u16),
-    Unknown(u16, u16),
+    GeogPrimeMeridianLongGeoKey(f64),
+    /// The 3 or 7 parameters of a Helmert transformation to WGS 84.
+    GeogTOWGS84GeoKey(Vec<f64>),
+
+    // Projected CRS parameter keys.
+    ProjectedCSTypeGeoKey(u16),
+    PCSCitationGeoKey(String),
+    ProjectionGeoKey(u16),
+    ProjCoordTransGeoKey(u16),
+    ProjLinearUnitsGeoKey(u16),
+    ProjLinearUnitSizeGeoKey(f64),
+    ProjStdParallel1GeoKey(f64),
+    ProjStdParallel2GeoKey(f64),
+    ProjNatOriginLongGeoKey(f64),
+    ProjNatOriginLatGeoKey(f64),
+    ProjFalseEastingGeoKey(f64),
+    ProjFalseNorthingGeoKey(f64),
+    ProjFalseOriginLongGeoKey(f64),
+    ProjFalseOriginLatGeoKey(f64),
+    ProjFalseOriginEastingGeoKey(f64),
+    ProjFalseOriginNorthingGeoKey(f64),
+    ProjCenterLongGeoKey(f64),
+    ProjCenterLatGeoKey(f64),
+    ProjCenterEastingGeoKey(f64),
+    ProjCenterNorthingGeoKey(f64),
+    ProjScaleAtNatOriginGeoKey(f64),
+    ProjScaleAtCenterGeoKey(f64),
+    ProjAzimuthAngleGeoKey(f64),
+    ProjStraightVertPoleLongGeoKey(f64),
+    ProjRectifiedGridAngleGeoKey(f64),
+
+    // Vertical CRS parameter keys.
+    VerticalCSTypeGeoKey(u16),
+    VerticalCitationGeoKey(String),
+    VerticalDatumGeoKey(u16),
+    VerticalUnitsGeoKey(u16),
+
+    /// A key that is not known, or whose value does not have the type the key requires.
+    Unknown(u16, GeoKeyValue),
 }
 
 impl GeoKey {
-    fn new(id: u16, value: u16) -> GeoKey {
-        match id {
-            1024 => GeoKey::GTModelTypeGeoKey(value),
-            1025 => GeoKey::GTRasterTypeGeoKey(value),
-            2048 => GeoKey::GeographicTypeGeoKey(value),
-            2050 => GeoKey::GeogGeodeticDatumGeoKey(value),
-            2051 => GeoKey::GeogPrimeMeridianGeoKey(value),
-            2052 => GeoKey::GeogLinearUnitsGeoKey(value),
-            2053 => GeoKey::GeogLinearUnitSizeGeoKey(value),
-            2054 => GeoKey::GeogAngularUnitsGeoKey(value),
-            2055 => GeoKey::GeogAngularUnitSizeGeoKey(value),
-            2056 => GeoKey::GeogEllipsoidGeoKey(value),
-            2057 => GeoKey::GeogSemiMajorAxisGeoKey(value),
-            2058 => GeoKey::GeogSemiMinorAxisGeoKey(value),
-            2059 => GeoKey::GeogInvFlatteningGeoKey(value),
-            2060 => GeoKey::GeogAzimuthUnitsGeoKey(value),
-            2061 => GeoKey::GeogPrimeMeridianLongGeoKey(value),
-            x => GeoKey::Unknown(x, value),
-        }
+    fn new(id: u16, value: GeoKeyValue) -> GeoKey {
+        let key = match id {
+            1024 => value.as_short().map(GeoKey::GTModelTypeGeoKey),
+            1025 => value.as_short().map(GeoKey::GTRasterTypeGeoKey),
+            1026 => value.as_ascii().map(GeoKey::GTCitationGeoKey),
+            2048 => value.as_short().map(GeoKey::GeographicTypeGeoKey),
+            2049 => value.as_ascii().map(GeoKey::GeogCitationGeoKey),
+            2050 => value.as_short().map(GeoKey::GeogGeodeticDatumGeoKey),
+            2051 => value.as_short().map(GeoKey::GeogPrimeMeridianGeoKey),
+            2052 => value.as_short().map(GeoKey::GeogLinearUnitsGeoKey),
+            2053 => value.as_double().map(GeoKey::GeogLinearUnitSizeGeoKey),
+            2054 => value.as_short().map(GeoKey::GeogAngularUnitsGeoKey),
+            2055 => value.as_double().map(GeoKey::GeogAngularUnitSizeGeoKey),
+            2056 => value.as_short().map(GeoKey::GeogEllipsoidGeoKey),
+            2057 => value.as_double().map(GeoKey::GeogSemiMajorAxisGeoKey),
+            2058 => value.as_double().map(GeoKey::GeogSemiMinorAxisGeoKey),
+            2059 => value.as_double().map(GeoKey::GeogInvFlatteningGeoKey),
+            2060 => value.as_short().map(GeoKey::GeogAzimuthUnitsGeoKey),
+            2061 => value.as_double().map(GeoKey::GeogPrimeMeridianLongGeoKey),
+            2062 => value.as_doubles().map(GeoKey::GeogTOWGS84GeoKey),
+            3072 => value.as_short().map(GeoKey::ProjectedCSTypeGeoKey),
+            3073 => value.as_ascii().map(GeoKey::PCSCitationGeoKey),
+            3074 => value.as_short().map(GeoKey::ProjectionGeoKey),
+            3075 => value.as_short().map(GeoKey::ProjCoordTransGeoKey),
+            3076 => value.as_short().map(GeoKey::ProjLinearUnitsGeoKey),
+            3077 => value.as_double().map(GeoKey::ProjLinearUnitSizeGeoKey),
+            3078 => value.as_double().map(GeoKey::ProjStdParallel1GeoKey),
+            3079 => value.as_double().map(GeoKey::ProjStdParallel2GeoKey),
+            3080 => value.as_double().map(GeoKey::ProjNatOriginLongGeoKey),
+            3081 => value.as_double().map(GeoKey::ProjNatOriginLatGeoKey),
+            3082 => value.as_double().map(GeoKey::ProjFalseEastingGeoKey),
+            3083 => value.as_double().map(GeoKey::ProjFalseNorthingGeoKey),
+            3084 => value.as_double().map(GeoKey::ProjFalseOriginLongGeoKey),
+            3085 => value.as_double().map(GeoKey::ProjFalseOriginLatGeoKey),
+            3086 => value.as_double().map(GeoKey::ProjFalseOriginEastingGeoKey),
+            3087 => value.as_double().map(GeoKey::ProjFalseOriginNorthingGeoKey),
+            3088 => value.as_double().map(GeoKey::ProjCenterLongGeoKey),
+            3089 => value.as_double().map(GeoKey::ProjCenterLatGeoKey),
+            3090 => value.as_double().map(GeoKey::ProjCenterEastingGeoKey),
+            3091 => value.as_double().map(GeoKey::ProjCenterNorthingGeoKey),
+            3092 => value.as_double().map(GeoKey::ProjScaleAtNatOriginGeoKey),
+            3093 => value.as_double().map(GeoKey::ProjScaleAtCenterGeoKey),
+            3094 => value.as_double().map(GeoKey::ProjAzimuthAngleGeoKey),
+            3095 => value
+                .as_double()
+                .map(GeoKey::ProjStraightVertPoleLongGeoKey),
+            3096 => value.as_double().map(GeoKey::ProjRectifiedGridAngleGeoKey),
+            4096 => value.as_short().map(GeoKey::VerticalCSTypeGeoKey),
+            4097 => value.as_ascii().map(GeoKey::VerticalCitationGeoKey),
+            4098 => value.as_short().map(GeoKey::VerticalDatumGeoKey),
+            4099 => value.as_short().map(GeoKey::VerticalUnitsGeoKey),
+            _ => None,
+        };
+        key.unwrap_or(GeoKey::Unknown(id, value))
     }
 }
@@ -1,42 +1,1233 @@
 extern crate geotiff as tiff;
 
-use tiff::TIFF;
+use std::fs::{self, File};
+use std::io::{BufReader, Cursor};
 
-//#[test]
+use tiff::crs::{
+    Ellipsoid, GeodeticDatum, GeographicCrs, PrimeMeridian, ProjectedCrs, Projection, Unit,
+};
+use tiff::tiff::{GeoKey, GeoKeyDirectoryInfo, GeoTiffVersion};
+use tiff::{
+    BoundingBox, Crs, Gcp, GcpTransformMethod, GeoTiffError, GeoTransform, RasterData, RasterType,
+    Sample, SampleType, TIFFTag, TIFF,
+};
+
+#[test]
 fn test_load() {
-    match TIFF::open("resources/marbles.tif") {
-        Ok(x) => println!("Read tiff {}", x),
-        Err(e) => println!("File I/O Error: {:?}", e),
+    let x = TIFF::open("resources/marbles.tif").unwrap();
+    assert_eq!(x.image_count(), 1);
+    assert!(x
+        .to_string()
+        .starts_with("TIFF(Image size: [1001, 1419, 3], "));
+    assert_eq!(x.get_geo_transform().unwrap(), None);
+}
+
+#[test]
+fn test_load_from_memory() {
+    let expected = TIFF::open("resources/zh_dem_25_lzw_tiled.tif").unwrap();
+    let bytes = fs::read("resources/zh_dem_25_lzw_tiled.tif").unwrap();
+    let images = [
+        TIFF::from_slice(&bytes).unwrap(),
+        TIFF::from_bytes(bytes.clone()).unwrap(),
+        TIFF::from_reader(Cursor::new(bytes.clone())).unwrap(),
+        TIFF::from_reader(BufReader::new(
+            File::open("resources/zh_dem_25_lzw_tiled.tif").unwrap(),
+        ))
+        .unwrap(),
+    ];
+    for x in images.iter() {
+        assert_eq!(x.get_value_at(45, 67).unwrap(), Some(Sample::I16(530)));
+        assert!(x.image_data().unwrap() == expected.image_data().unwrap());
     }
+
+    match TIFF::from_bytes(&b"II\x2a\x00"[..]) {
+        Err(GeoTiffError::TruncatedData) => {}
+        other => panic!(
+            "Expected a truncated data error, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+    match TIFF::from_bytes(*b"II\x2a\x00\x08\x00\x00\x00") {
+        Err(GeoTiffError::TruncatedData) => {}
+        other => panic!(
+            "Expected a truncated data error, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+    match TIFF::from_bytes(Vec::new()) {
+        Err(GeoTiffError::TruncatedData) => {}
+        other => panic!(
+            "Expected a truncated data error, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+}
+
+#[test]
+fn test_load_predictor() {
+    // LZW compressed RGB image with horizontal differencing.
+    let x = TIFF::open("resources/marbles.tif").unwrap();
+    assert_eq!(x.image_data().unwrap().height(), 1001);
+    assert_eq!(x.image_data().unwrap().width(), 1419);
+    assert_eq!(
+        x.image_data().unwrap().pixel(0, 0).unwrap(),
+        vec![Sample::U8(0), Sample::U8(0), Sample::U8(0)]
+    );
+    assert_eq!(
+        x.image_data().unwrap().pixel(700, 500).unwrap(),
+        vec![Sample::U8(151), Sample::U8(163), Sample::U8(170)]
+    );
+    assert_eq!(
+        x.image_data().unwrap().pixel(456, 123).unwrap(),
+        vec![Sample::U8(168), Sample::U8(61), Sample::U8(0)]
+    );
+
+    // Big endian 16 bit samples.
+    assert_same_as_zh_dem_25("resources/zh_dem_25_lzw_predictor.tif");
 }
 
 #[test]
 fn test_load_2() {
     match TIFF::open("resources/zh_dem_25.tif") {
         Ok(x) => {
-            assert_eq!(x.image_data.len(), 366);
-            assert_eq!(x.image_data[0].len(), 399);
+            assert_eq!(x.image_data().unwrap().height(), 366);
+            assert_eq!(x.image_data().unwrap().width(), 399);
 
-            assert_eq!(x.get_value_at(0, 0), 551);
-            assert_eq!(x.get_value_at(45, 67), 530);
-            assert_eq!(x.get_value_at(142, 325), 587);
+            assert_eq!(x.get_value_at(0, 0).unwrap(), Some(Sample::I16(551)));
+            assert_eq!(x.get_value_at(45, 67).unwrap(), Some(Sample::I16(530)));
+            assert_eq!(x.get_value_at(142, 325).unwrap(), Some(Sample::I16(587)));
         },
         Err(e) => println!("File I/O Error: {:?}", e),
     }
 }
 
+#[test]
+fn test_load_packbits() {
+    match TIFF::open("resources/zh_dem_25_packbits.tif") {
+        Ok(x) => {
+            assert_eq!(x.image_data().unwrap().height(), 366);
+            assert_eq!(x.image_data().unwrap().width(), 399);
+
+            assert_eq!(x.get_value_at(0, 0).unwrap(), Some(Sample::I16(551)));
+            assert_eq!(x.get_value_at(45, 67).unwrap(), Some(Sample::I16(530)));
+            assert_eq!(x.get_value_at(142, 325).unwrap(), Some(Sample::I16(587)));
+        }
+        Err(e) => panic!("File I/O Error: {:?}", e),
+    }
+}
+
 // TODO Not supported yet, as this uses TileByteCounts instead of StripByteCounts.
-//#[test]
+#[test]
+#[ignore = "resources/large_tif/DEM_ZH.tif is not part of the repository"]
 fn test_load_3() {
     match TIFF::open("resources/large_tif/DEM_ZH.tif") {
         Ok(x) => {
-            assert_eq!(x.image_data.len(), 366);
-            assert_eq!(x.image_data[0].len(), 399);
+            assert_eq!(x.image_data().unwrap().height(), 366);
+            assert_eq!(x.image_data().unwrap().width(), 399);
 
-            assert_eq!(x.get_value_at(0, 0), 551);
-            assert_eq!(x.get_value_at(45, 67), 530);
-            assert_eq!(x.get_value_at(142, 325), 587);
+            assert_eq!(x.get_value_at(0, 0).unwrap(), Some(Sample::I16(551)));
+            assert_eq!(x.get_value_at(45, 67).unwrap(), Some(Sample::I16(530)));
+            assert_eq!(x.get_value_at(142, 325).unwrap(), Some(Sample::I16(587)));
         },
         Err(e) => println!("File I/O Error: {:?}", e),
     }
 }
+
+#[test]
+fn test_load_overview() {
+    let x = TIFF::open("resources/zh_dem_25_overview.tif").unwrap();
+    assert_eq!(x.image_count(), 2);
+    assert_eq!(x.image_data().unwrap().height(), 366);
+    assert_eq!(x.get_value_at(45, 67).unwrap(), Some(Sample::I16(530)));
+
+    let overview = x.get_image_data(1).unwrap();
+    assert_eq!(overview.height(), 183);
+    assert_eq!(overview.width(), 200);
+    assert_eq!(x.get_value_of_image_at(1, 0, 0), Some(Sample::I16(551)));
+    assert_eq!(x.get_value_of_image_at(1, 71, 162), Some(Sample::I16(589)));
+    assert!(matches!(
+        x.get_image_data(2),
+        Err(GeoTiffError::NoSuchImage(2))
+    ));
+}
+
+#[test]
+fn test_cyclic_ifd_chain() {
+    match TIFF::open("resources/cyclic_ifd.tif") {
+        Err(GeoTiffError::InvalidIFD(_)) => {}
+        other => panic!("Expected an invalid IFD error, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_malformed_input() {
+    match TIFF::open("resources/invalid_header.tif") {
+        Err(GeoTiffError::InvalidHeader(_)) => {}
+        other => panic!(
+            "Expected an invalid header error, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+    // Opening only reads the IFDs, errors in the image data surface once it is read.
+    let x = TIFF::open("resources/zh_dem_25_truncated.tif").unwrap();
+    match x.image_data() {
+        Err(GeoTiffError::TruncatedData) => {}
+        other => panic!(
+            "Expected a truncated data error, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+    match x.get_value_at(300, 0) {
+        Err(GeoTiffError::TruncatedData) => {}
+        other => panic!("Expected a truncated data error, got {:?}", other),
+    }
+    assert_eq!(x.get_value_at(366, 0).unwrap(), None);
+    assert_eq!(x.get_value_at(0, 399).unwrap(), None);
+    let x = TIFF::open("resources/zh_dem_25_jpeg2000.tif").unwrap();
+    match x.read_window(0, 0, 1, 1, &[0]) {
+        Err(GeoTiffError::UnsupportedCompression(34712)) => {}
+        other => panic!(
+            "Expected an unsupported compression, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+    // The bands of this RGB image are stored in separate planes.
+    let x = TIFF::open("resources/rgb_planar.tif").unwrap();
+    match x.read_window(0, 0, 1, 1, &[1]) {
+        Err(GeoTiffError::UnsupportedPlanarConfiguration(2)) => {}
+        other => panic!(
+            "Expected an unsupported planar configuration, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+    assert!(x.image_data().is_err());
+    assert!(x.sampler(1 << 20).is_err());
+    let x = TIFF::open("resources/zh_dem_25_no_strip_offsets.tif").unwrap();
+    match x.image_data() {
+        Err(GeoTiffError::MissingTag(TIFFTag::StripOffsetsTag)) => {}
+        other => panic!("Expected a missing tag error, got {:?}", other.map(|_| ())),
+    }
+    match TIFF::open("resources/does_not_exist.tif") {
+        Err(GeoTiffError::Io(_)) => {}
+        other => panic!("Expected an I/O error, got {:?}", other.map(|_| ())),
+    }
+    // Images of 2^30 * 2^30 pixels in a single strip of a few bytes are rejected before
+    // allocating memory for them.
+    for path in [
+        "resources/huge_dimensions.tif",
+        "resources/huge_dimensions_lzw.tif",
+    ] {
+        let x = TIFF::open(path).unwrap();
+        match x.image_data() {
+            Err(GeoTiffError::CorruptData(_)) => {}
+            other => panic!("Expected corrupt data, got {:?}", other.map(|_| ())),
+        }
+        assert!(x.read_window(0, 0, 1, 1, &[0]).is_err());
+        assert!(x.sampler(1 << 20).unwrap().get(0, 0, 0).is_err());
+        assert!(x.get_value_at(0, 0).is_err());
+    }
+    // 2^40 * 2^40 pixels in tiles of 16 * 16 pixels are more tiles than can be counted.
+    let x = TIFF::open("resources/huge_dimensions_bigtiff.tif").unwrap();
+    match x.block_count() {
+        Err(GeoTiffError::InvalidTagValue(TIFFTag::ImageWidthTag, _)) => {}
+        other => panic!("Expected an invalid tag value, got {:?}", other),
+    }
+    assert!(x.image_data().is_err());
+    // A single strip of 2^40 * 2^40 pixels is larger than any buffer.
+    let x = TIFF::open("resources/huge_strip_bigtiff.tif").unwrap();
+    match x.read_window(0, 0, 1, 1, &[0]) {
+        Err(GeoTiffError::InvalidTagValue(TIFFTag::RowsPerStripTag, _)) => {}
+        other => panic!("Expected an invalid tag value, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_load_bigtiff() {
+    let x = TIFF::open("resources/zh_dem_25_bigtiff.tif").unwrap();
+    assert_eq!(x.image_data().unwrap().height(), 366);
+    assert_eq!(x.image_data().unwrap().width(), 399);
+
+    assert_eq!(x.get_value_at(0, 0).unwrap(), Some(Sample::I16(551)));
+    assert_eq!(x.get_value_at(45, 67).unwrap(), Some(Sample::I16(530)));
+    assert_eq!(x.get_value_at(142, 325).unwrap(), Some(Sample::I16(587)));
+}
+
+/// Checks that the image at `path` contains the very same data as `resources/zh_dem_25.tif`.
+fn assert_same_as_zh_dem_25(path: &str) {
+    let expected = TIFF::open("resources/zh_dem_25.tif").unwrap();
+    let x = TIFF::open(path).unwrap();
+    assert_eq!(x.image_data().unwrap().height(), 366);
+    assert_eq!(x.image_data().unwrap().width(), 399);
+
+    assert_eq!(x.get_value_at(0, 0).unwrap(), Some(Sample::I16(551)));
+    assert_eq!(x.get_value_at(45, 67).unwrap(), Some(Sample::I16(530)));
+    assert_eq!(x.get_value_at(142, 325).unwrap(), Some(Sample::I16(587)));
+    assert!(x.image_data().unwrap() == expected.image_data().unwrap());
+}
+
+#[test]
+fn test_load_lzw() {
+    assert_same_as_zh_dem_25("resources/zh_dem_25_lzw.tif");
+    assert_same_as_zh_dem_25("resources/zh_dem_25_lzw_tiled.tif");
+    assert_same_as_zh_dem_25("resources/zh_dem_25_lzw_old_style.tif");
+}
+
+#[test]
+fn test_load_deflate() {
+    assert_same_as_zh_dem_25("resources/zh_dem_25_deflate.tif");
+    assert_same_as_zh_dem_25("resources/zh_dem_25_deflate_tiled.tif");
+}
+
+#[test]
+fn test_load_floating_point_predictor() {
+    let x = TIFF::open("resources/zh_dem_25_float32_predictor.tif").unwrap();
+    assert_eq!(x.get_value_at(0, 0).unwrap(), Some(Sample::F32(551.5)));
+    assert_eq!(x.get_value_at(45, 67).unwrap(), Some(Sample::F32(530.5)));
+    assert_eq!(x.get_value_at(142, 325).unwrap(), Some(Sample::F32(587.5)));
+
+    let x = TIFF::open("resources/zh_dem_25_float64_predictor.tif").unwrap();
+    assert_eq!(x.get_value_at(0, 0).unwrap(), Some(Sample::F64(551.5)));
+    assert_eq!(x.get_value_at(45, 67).unwrap(), Some(Sample::F64(530.5)));
+    assert_eq!(x.get_value_at(142, 325).unwrap(), Some(Sample::F64(587.5)));
+}
+
+#[test]
+fn test_load_signed_samples() {
+    let x = TIFF::open("resources/bathymetry_int16.tif").unwrap();
+    assert_eq!(x.get_value_at(0, 0).unwrap(), Some(Sample::I16(-4321)));
+    assert_eq!(x.get_value_at(0, 1).unwrap(), Some(Sample::I16(-1)));
+    assert_eq!(x.get_value_at(1, 1).unwrap(), Some(Sample::I16(-32768)));
+    assert_eq!(x.get_value_at(1, 2).unwrap(), Some(Sample::I16(32767)));
+    assert_eq!(x.get_value_at(1, 2).unwrap().unwrap().to_f64(), 32767.0);
+}
+
+#[test]
+fn test_geo_keys() {
+    let x = TIFF::open("resources/zh_dem_25_lv03.tif").unwrap();
+    let keys = x.ifds[0].get_geo_keys().unwrap();
+    assert_eq!(
+        keys,
+        vec![
+            GeoKey::GTModelTypeGeoKey(1),
+            GeoKey::GTRasterTypeGeoKey(1),
+            GeoKey::GTCitationGeoKey("CH1903 / LV03".to_string()),
+            GeoKey::GeogCitationGeoKey("CH1903".to_string()),
+            GeoKey::GeogAngularUnitsGeoKey(9102),
+            GeoKey::GeogSemiMajorAxisGeoKey(6377397.155),
+            GeoKey::GeogInvFlatteningGeoKey(299.1528128),
+            GeoKey::GeogTOWGS84GeoKey(vec![674.374, 15.056, 405.346]),
+            GeoKey::ProjectedCSTypeGeoKey(21781),
+            GeoKey::ProjLinearUnitsGeoKey(9001),
+            // Stored within the trailing shorts of the directory.
+            GeoKey::VerticalUnitsGeoKey(9001),
+        ]
+    );
+
+    let directory = x.ifds[0].get_geo_key_directory().unwrap();
+    assert_eq!(directory.version, GeoTiffVersion::V1_0);
+    assert_eq!(directory.keys, keys);
+
+    // The plain file has no GeoKey directory at all.
+    let x = TIFF::open("resources/zh_dem_25.tif").unwrap();
+    assert!(x.ifds[0].get_geo_keys().is_err());
+}
+
+#[test]
+fn test_geo_key_directory_version() {
+    let x = TIFF::open("resources/geokeys_v1_1.tif").unwrap();
+    let directory = x.ifds[0].get_geo_key_directory().unwrap();
+    assert_eq!(
+        directory.header,
+        GeoKeyDirectoryInfo {
+            directory_version: 1,
+            revision: 1,
+            minor_revision: 1,
+            number_of_keys: 3,
+        }
+    );
+    assert_eq!(directory.version, GeoTiffVersion::V1_1);
+    assert_eq!(directory.keys[2], GeoKey::GeographicTypeGeoKey(4326));
+
+    let x = TIFF::open("resources/geokeys_unsupported_version.tif").unwrap();
+    match x.ifds[0].get_geo_key_directory() {
+        Err(GeoTiffError::UnsupportedGeoKeyVersion(1, 2, 0)) => {}
+        other => panic!("Expected an unsupported version, got {:?}", other),
+    }
+    // The georeferencing tags can still be used.
+    assert!(x.get_geo_transform().unwrap().is_some());
+    assert!(x.get_corners().is_ok());
+}
+
+#[test]
+fn test_geo_transform() {
+    // Tiepoint and pixel scale.
+    let x = TIFF::open("resources/zh_dem_25_lv03.tif").unwrap();
+    assert_eq!(
+        x.get_geo_transform().unwrap(),
+        Some(GeoTransform([677562.5, 25.0, 0.0, 253012.5, 0.0, -25.0]))
+    );
+
+    // Rotated and sheared ModelTransformation.
+    let x = TIFF::open("resources/model_transformation.tif").unwrap();
+    assert_eq!(
+        x.get_geo_transform().unwrap(),
+        Some(GeoTransform([1000.0, 8.0, 2.0, 2000.0, 1.5, -9.0]))
+    );
+
+    // No georeferencing at all.
+    let x = TIFF::open("resources/marbles.tif").unwrap();
+    assert_eq!(x.get_geo_transform().unwrap(), None);
+}
+
+#[test]
+fn test_coordinate_conversion() {
+    let x = TIFF::open("resources/zh_dem_25_lv03.tif").unwrap();
+    assert_eq!(x.pixel_to_model(0.0, 0.0).unwrap(), (677562.5, 253012.5));
+    assert_eq!(x.pixel_to_model(67.5, 45.5).unwrap(), (679250.0, 251875.0));
+    assert_eq!(x.model_to_pixel(679250.0, 251875.0).unwrap(), (67.5, 45.5));
+    assert_eq!(x.get_value_at(45, 67).unwrap(), Some(Sample::I16(530)));
+    assert_eq!(
+        x.get_value_at_coord(679250.0, 251875.0),
+        Some(Sample::I16(530))
+    );
+    // Anywhere within the pixel.
+    assert_eq!(
+        x.get_value_at_coord(679237.6, 251887.4),
+        Some(Sample::I16(530))
+    );
+    assert_eq!(x.get_value_at_coord(677562.0, 253000.0), None);
+    assert_eq!(x.get_value_at_coord(677570.0, 253013.0), None);
+    assert_eq!(x.get_value_at_coord(687537.5, 253000.0), None);
+
+    // Rotated and sheared.
+    let x = TIFF::open("resources/model_transformation.tif").unwrap();
+    let (model_x, model_y) = x.pixel_to_model(1.25, 0.75).unwrap();
+    assert_eq!((model_x, model_y), (1011.5, 1995.125));
+    let (col, row) = x.model_to_pixel(model_x, model_y).unwrap();
+    assert!((col - 1.25).abs() < 1e-9 && (row - 0.75).abs() < 1e-9);
+    assert_eq!(x.get_value_at_coord(model_x, model_y), Some(Sample::U16(2)));
+
+    let x = TIFF::open("resources/marbles.tif").unwrap();
+    assert!(matches!(
+        x.pixel_to_model(0.0, 0.0),
+        Err(GeoTiffError::NoGeoTransform)
+    ));
+    assert_eq!(x.get_value_at_coord(0.5, 0.5), None);
+}
+
+#[test]
+fn test_pixel_is_point() {
+    let x = TIFF::open("resources/zh_dem_25_lv03.tif").unwrap();
+    assert_eq!(x.get_raster_type(), RasterType::PixelIsArea);
+
+    let mut x = TIFF::open("resources/pixel_is_point.tif").unwrap();
+    assert_eq!(x.get_raster_type(), RasterType::PixelIsPoint);
+    // The tiepoint refers to the centre of the top left pixel.
+    assert_eq!(
+        x.get_geo_transform().unwrap(),
+        Some(GeoTransform([7.75, 0.5, 0.0, 47.25, 0.0, -0.5]))
+    );
+    assert_eq!(x.pixel_to_model(0.5, 0.5).unwrap(), (8.0, 47.0));
+    assert_eq!(x.get_value_at_coord(8.1, 46.9), Some(Sample::U16(1)));
+    assert_eq!(x.get_value_at_coord(8.3, 46.9), Some(Sample::U16(2)));
+
+    x.point_geo_ignore = true;
+    assert_eq!(
+        x.get_geo_transform().unwrap(),
+        Some(GeoTransform([8.0, 0.5, 0.0, 47.0, 0.0, -0.5]))
+    );
+    assert_eq!(x.pixel_to_model(0.0, 0.0).unwrap(), (8.0, 47.0));
+    assert_eq!(x.get_value_at_coord(8.1, 46.9), Some(Sample::U16(1)));
+    assert_eq!(x.get_value_at_coord(8.3, 46.9), Some(Sample::U16(1)));
+}
+
+#[test]
+fn test_gcps() {
+    let mut x = TIFF::open("resources/gcps.tif").unwrap();
+    let gcps = x.get_gcps().unwrap();
+    assert_eq!(gcps.len(), 5);
+    assert_eq!(
+        gcps[1],
+        Gcp {
+            col: 4.0,
+            row: 0.0,
+            x: 1040.0,
+            y: 2004.0,
+            z: 0.0
+        }
+    );
+    assert_eq!(x.get_geo_transform().unwrap(), None);
+
+    let assert_close = |actual: (f64, f64), expected: (f64, f64), tolerance: f64| {
+        assert!(
+            (actual.0 - expected.0).abs() < tolerance && (actual.1 - expected.1).abs() < tolerance,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    };
+
+    // The least squares fit spreads the error of the last GCP over all of them.
+    assert_close(x.pixel_to_model(0.5, 3.5).unwrap(), (1001.6, 1965.6), 1e-9);
+    // The inverse is fitted separately, so it is only roughly the inverse.
+    assert_close(x.model_to_pixel(1001.6, 1965.6).unwrap(), (0.5, 3.5), 1e-3);
+    assert_eq!(x.get_value_at_coord(1001.5, 1965.5), Some(Sample::U16(12)));
+    assert_eq!(x.get_value_at_coord(1031.5, 1968.5), Some(Sample::U16(15)));
+    assert_eq!(x.get_value_at_coord(1042.0, 1963.0), None);
+
+    // A thin plate spline matches every GCP.
+    x.gcp_transform_method = GcpTransformMethod::ThinPlateSpline;
+    for gcp in &gcps {
+        assert_close(
+            x.pixel_to_model(gcp.col, gcp.row).unwrap(),
+            (gcp.x, gcp.y),
+            1e-9,
+        );
+        assert_close(
+            x.model_to_pixel(gcp.x, gcp.y).unwrap(),
+            (gcp.col, gcp.row),
+            1e-9,
+        );
+    }
+
+    // Higher order polynomials need more GCPs.
+    x.gcp_transform_method = GcpTransformMethod::Polynomial2;
+    for _ in 0..2 {
+        match x.get_gcp_transform() {
+            Err(GeoTiffError::InvalidTagValue(TIFFTag::ModelTiepointTag, _)) => {}
+            other => panic!("Expected an invalid tag value, got {:?}", other),
+        }
+    }
+    x.gcp_transform_method = GcpTransformMethod::Polynomial1;
+    assert!(x.get_gcp_transform().unwrap().is_some());
+
+    // A single tiepoint without a pixel scale does not georeference the image.
+    let x = TIFF::open("resources/single_tiepoint.tif").unwrap();
+    assert_eq!(x.get_gcps().unwrap().len(), 1);
+    assert!(x.get_gcp_transform().unwrap().is_none());
+    assert!(matches!(
+        x.pixel_to_model(0.0, 0.0),
+        Err(GeoTiffError::NoGeoTransform)
+    ));
+}
+
+#[test]
+fn test_extent() {
+    let x = TIFF::open("resources/zh_dem_25_lv03.tif").unwrap();
+    assert_eq!(
+        x.get_corners().unwrap(),
+        [
+            (677562.5, 253012.5),
+            (687537.5, 253012.5),
+            (687537.5, 243862.5),
+            (677562.5, 243862.5)
+        ]
+    );
+    let bbox = x.get_bounding_box().unwrap();
+    assert_eq!(
+        bbox,
+        BoundingBox {
+            min_x: 677562.5,
+            min_y: 243862.5,
+            max_x: 687537.5,
+            max_y: 253012.5
+        }
+    );
+    assert_eq!((bbox.width(), bbox.height()), (9975.0, 9150.0));
+    assert!(bbox.contains(679250.0, 251875.0));
+
+    // The footprint of a rotated and sheared raster is no rectangle.
+    let x = TIFF::open("resources/model_transformation.tif").unwrap();
+    assert_eq!(
+        x.get_corners().unwrap(),
+        [
+            (1000.0, 2000.0),
+            (1016.0, 2003.0),
+            (1020.0, 1985.0),
+            (1004.0, 1982.0)
+        ]
+    );
+    assert_eq!(
+        x.get_bounding_box().unwrap(),
+        BoundingBox {
+            min_x: 1000.0,
+            min_y: 1982.0,
+            max_x: 1020.0,
+            max_y: 2003.0
+        }
+    );
+
+    let x = TIFF::open("resources/marbles.tif").unwrap();
+    assert!(x.get_bounding_box().is_err());
+}
+
+#[test]
+fn test_crs() {
+    let x = TIFF::open("resources/zh_dem_25_lv03.tif").unwrap();
+    let crs = x.get_crs().unwrap();
+    assert_eq!(crs.epsg_code(), Some(21781));
+    match crs {
+        Crs::Projected(ref crs) => assert_eq!(crs.name, "CH1903 / LV03"),
+        ref crs => panic!("Expected a projected CRS, got {:?}", crs),
+    }
+
+    let x = TIFF::open("resources/lv95.tif").unwrap();
+    let crs = x.get_crs().unwrap();
+    assert_eq!(crs.epsg_code(), Some(2056));
+    assert_eq!(
+        crs.to_wkt().unwrap(),
+        "PROJCRS[\"CH1903+ / LV95\",BASEGEOGCRS[\"CH1903+\",DATUM[\"CH1903+\",\
+         ELLIPSOID[\"Bessel 1841\",6377397.155,299.1528128,LENGTHUNIT[\"metre\",1]]],\
+         PRIMEM[\"Greenwich\",0,ANGLEUNIT[\"degree\",0.017453292519943295]],ID[\"EPSG\",4150]],\
+         CONVERSION[\"unknown\",METHOD[\"Hotine Oblique Mercator (variant B)\",ID[\"EPSG\",9815]],\
+         PARAMETER[\"Latitude of projection centre\",46.9524055555556,\
+         ANGLEUNIT[\"degree\",0.017453292519943295],ID[\"EPSG\",8811]],\
+         PARAMETER[\"Longitude of projection centre\",7.43958333333333,\
+         ANGLEUNIT[\"degree\",0.017453292519943295],ID[\"EPSG\",8812]],\
+         PARAMETER[\"Azimuth of initial line\",90,\
+         ANGLEUNIT[\"degree\",0.017453292519943295],ID[\"EPSG\",8813]],\
+         PARAMETER[\"Angle from Rectified to Skew Grid\",90,\
+         ANGLEUNIT[\"degree\",0.017453292519943295],ID[\"EPSG\",8814]],\
+         PARAMETER[\"Scale factor on initial line\",1,SCALEUNIT[\"unity\",1],ID[\"EPSG\",8815]],\
+         PARAMETER[\"Easting at projection centre\",2600000,LENGTHUNIT[\"metre\",1],\
+         ID[\"EPSG\",8816]],\
+         PARAMETER[\"Northing at projection centre\",1200000,LENGTHUNIT[\"metre\",1],\
+         ID[\"EPSG\",8817]]],\
+         CS[Cartesian,2],AXIS[\"easting (E)\",east,ORDER[1],LENGTHUNIT[\"metre\",1]],\
+         AXIS[\"northing (N)\",north,ORDER[2],LENGTHUNIT[\"metre\",1]],ID[\"EPSG\",2056]]"
+    );
+
+    let x = TIFF::open("resources/geokeys_v1_1.tif").unwrap();
+    let crs = x.get_crs().unwrap();
+    assert_eq!(crs.epsg_code(), Some(4326));
+    assert_eq!(
+        crs.to_wkt().unwrap(),
+        "GEOGCRS[\"WGS 84\",DATUM[\"World Geodetic System 1984\",\
+         ELLIPSOID[\"WGS 84\",6378137,298.257223563,LENGTHUNIT[\"metre\",1]]],\
+         PRIMEM[\"Greenwich\",0,ANGLEUNIT[\"degree\",0.017453292519943295]],\
+         CS[ellipsoidal,2],\
+         AXIS[\"geodetic latitude (Lat)\",north,ORDER[1],ANGLEUNIT[\"degree\",0.017453292519943295]],\
+         AXIS[\"geodetic longitude (Lon)\",east,ORDER[2],ANGLEUNIT[\"degree\",0.017453292519943295]],\
+         ID[\"EPSG\",4326]]"
+    );
+
+    // Everything user-defined, with angles in grad.
+    let x = TIFF::open("resources/user_defined_crs.tif").unwrap();
+    let crs = x.get_crs().unwrap();
+    assert_eq!(crs.epsg_code(), None);
+    let wkt = crs.to_wkt().unwrap();
+    assert!(wkt.starts_with(
+        "PROJCRS[\"Custom TM\",BASEGEOGCRS[\"Custom GRS 1980\",DATUM[\"Custom GRS 1980\",\
+         ELLIPSOID[\"unknown\",6378137,298.257222101,LENGTHUNIT[\"metre\",1]]]"
+    ));
+    assert!(wkt.contains(
+        "METHOD[\"Transverse Mercator\",ID[\"EPSG\",9807]],\
+         PARAMETER[\"Latitude of natural origin\",0,"
+    ));
+    assert!(wkt.contains("PARAMETER[\"Longitude of natural origin\",9,"));
+    assert!(!wkt.contains("ID[\"EPSG\",32767]"));
+
+    // Mercator with a standard parallel instead of a scale factor, using the example of EPSG
+    // guidance note 7-2 for the Mercator (variant B) method.
+    let x = TIFF::open("resources/mercator_2sp.tif").unwrap();
+    let crs = match x.get_crs().unwrap() {
+        Crs::Projected(crs) => crs,
+        other => panic!("Expected a projected CRS, got {:?}", other),
+    };
+    let (x, y) = crs.project(53.0, 53.0);
+    assert!((x - 165704.29).abs() < 0.01 && (y - 5171848.07).abs() < 0.01);
+
+    // CT_ObliqueMercator is variant A, while variant B is given by its EPSG method code. Both
+    // describe the same grid with the example of EPSG guidance note 7-2.
+    for (path, method) in [
+        ("resources/oblique_mercator_a.tif", 9812),
+        ("resources/oblique_mercator_b.tif", 9815),
+    ] {
+        let crs = TIFF::open(path).unwrap().get_crs().unwrap();
+        let wkt = crs.to_wkt().unwrap();
+        assert!(wkt.contains(&format!("ID[\"EPSG\",{}]", method)));
+        let crs = match crs {
+            Crs::Projected(crs) => crs,
+            other => panic!("Expected a projected CRS, got {:?}", other),
+        };
+        let (x, y) = crs.project(dms(115.0, 48.0, 19.8196), dms(5.0, 23.0, 14.1129));
+        assert!((x - 679245.73).abs() < 0.01 && (y - 596562.78).abs() < 0.01);
+    }
+
+    let x = TIFF::open("resources/zh_dem_25.tif").unwrap();
+    assert!(x.get_crs().is_err());
+}
+
+fn projected_crs(
+    a: f64,
+    inverse_flattening: f64,
+    projection: Projection,
+    unit: f64,
+) -> ProjectedCrs {
+    let ellipsoid = Ellipsoid {
+        name: "test".to_string(),
+        semi_major_axis: a,
+        inverse_flattening,
+    };
+    ProjectedCrs {
+        name: "test".to_string(),
+        epsg: None,
+        base: GeographicCrs {
+            name: "test".to_string(),
+            epsg: None,
+            datum: GeodeticDatum {
+                name: "test".to_string(),
+                ellipsoid,
+                to_wgs84: Some(vec![0.0; 3]),
+            },
+            prime_meridian: PrimeMeridian::greenwich(),
+            angular_unit: Unit::degree(),
+        },
+        projection,
+        linear_unit: Unit {
+            name: "test".to_string(),
+            factor: unit,
+        },
+    }
+}
+
+fn dms(degrees: f64, minutes: f64, seconds: f64) -> f64 {
+    degrees.signum() * (degrees.abs() + minutes / 60.0 + seconds / 3600.0)
+}
+
+#[test]
+fn test_projection() {
+    // The examples of the EPSG guidance note 7-2, as (crs, lon, lat, easting, northing).
+    let examples = [
+        // OSGB 1936 / British National Grid
+        (
+            projected_crs(
+                6377563.396,
+                299.3249646,
+                Projection::TransverseMercator {
+                    latitude_of_origin: 49.0,
+                    longitude_of_origin: -2.0,
+                    scale_factor: 0.9996012717,
+                    false_easting: 400000.0,
+                    false_northing: -100000.0,
+                },
+                1.0,
+            ),
+            0.5,
+            50.5,
+            577274.99,
+            69740.50,
+        ),
+        // Makassar / NEIEZ
+        (
+            projected_crs(
+                6377397.155,
+                299.1528128,
+                Projection::Mercator {
+                    latitude_of_origin: 0.0,
+                    longitude_of_origin: 110.0,
+                    scale_factor: 0.997,
+                    false_easting: 3900000.0,
+                    false_northing: 900000.0,
+                },
+                1.0,
+            ),
+            120.0,
+            -3.0,
+            5009726.58,
+            569150.82,
+        ),
+        // WGS 84 / Pseudo-Mercator
+        (
+            projected_crs(
+                6378137.0,
+                298.257223563,
+                Projection::WebMercator {
+                    latitude_of_origin: 0.0,
+                    longitude_of_origin: 0.0,
+                    false_easting: 0.0,
+                    false_northing: 0.0,
+                },
+                1.0,
+            ),
+            -dms(100.0, 20.0, 0.0),
+            dms(24.0, 22.0, 54.433),
+            -11169055.58,
+            2800000.00,
+        ),
+        // JAD69 / Jamaica National Grid
+        (
+            projected_crs(
+                6378206.4,
+                294.9786982,
+                Projection::LambertConformalConic1SP {
+                    latitude_of_origin: 18.0,
+                    longitude_of_origin: -77.0,
+                    scale_factor: 1.0,
+                    false_easting: 250000.0,
+                    false_northing: 150000.0,
+                },
+                1.0,
+            ),
+            -dms(76.0, 56.0, 37.26),
+            dms(17.0, 55.0, 55.80),
+            255966.58,
+            142493.51,
+        ),
+        // NAD27 / Texas South Central, in US survey feet
+        (
+            projected_crs(
+                6378206.4,
+                294.9786982,
+                Projection::LambertConformalConic2SP {
+                    latitude_of_false_origin: dms(27.0, 50.0, 0.0),
+                    longitude_of_false_origin: -99.0,
+                    standard_parallel_1: dms(28.0, 23.0, 0.0),
+                    standard_parallel_2: dms(30.0, 17.0, 0.0),
+                    false_easting: 2000000.0,
+                    false_northing: 0.0,
+                },
+                0.3048006096012192,
+            ),
+            -96.0,
+            28.5,
+            2963503.91,
+            254759.80,
+        ),
+        // Timbalai 1948 / RSO Borneo
+        (
+            projected_crs(
+                6377298.556,
+                300.8017,
+                Projection::ObliqueMercator {
+                    latitude_of_centre: 4.0,
+                    longitude_of_centre: 115.0,
+                    azimuth: dms(53.0, 18.0, 56.9537),
+                    rectified_grid_angle: dms(53.0, 7.0, 48.3685),
+                    scale_factor: 0.99984,
+                    false_easting: 590476.87,
+                    false_northing: 442857.65,
+                },
+                1.0,
+            ),
+            dms(115.0, 48.0, 19.8196),
+            dms(5.0, 23.0, 14.1129),
+            679245.73,
+            596562.78,
+        ),
+        // Timbalai 1948 / RSO Borneo, relative to the natural origin
+        (
+            projected_crs(
+                6377298.556,
+                300.8017,
+                Projection::ObliqueMercatorA {
+                    latitude_of_centre: 4.0,
+                    longitude_of_centre: 115.0,
+                    azimuth: dms(53.0, 18.0, 56.9537),
+                    rectified_grid_angle: dms(53.0, 7.0, 48.3685),
+                    scale_factor: 0.99984,
+                    false_easting: 0.0,
+                    false_northing: 0.0,
+                },
+                1.0,
+            ),
+            dms(115.0, 48.0, 19.8196),
+            dms(5.0, 23.0, 14.1129),
+            679245.73,
+            596562.78,
+        ),
+        // NAD83 / Conus Albers, at its origin
+        (
+            projected_crs(
+                6378137.0,
+                298.257222101,
+                Projection::AlbersEqualArea {
+                    latitude_of_false_origin: 23.0,
+                    longitude_of_false_origin: -96.0,
+                    standard_parallel_1: 29.5,
+                    standard_parallel_2: 45.5,
+                    false_easting: 0.0,
+                    false_northing: 0.0,
+                },
+                1.0,
+            ),
+            -96.0,
+            23.0,
+            0.0,
+            0.0,
+        ),
+    ];
+    for (crs, lon, lat, easting, northing) in examples.iter() {
+        let (x, y) = crs.project(*lon, *lat);
+        assert!(
+            (x - easting).abs() < 0.01 && (y - northing).abs() < 0.01,
+            "{:?}: expected ({}, {}), got ({}, {})",
+            crs.projection,
+            easting,
+            northing,
+            x,
+            y
+        );
+        for &(lon, lat) in [(*lon, *lat), (lon + 1.5, lat - 2.5)].iter() {
+            let (x, y) = crs.project(lon, lat);
+            let (lon2, lat2) = crs.unproject(x, y);
+            assert!(
+                (lon - lon2).abs() < 1e-9 && (lat - lat2).abs() < 1e-9,
+                "{:?}: ({}, {}) round trips to ({}, {})",
+                crs.projection,
+                lon,
+                lat,
+                lon2,
+                lat2
+            );
+        }
+    }
+
+    // UTM zone 32N on its central meridian, where the northing is the scaled meridian arc.
+    let x = TIFF::open("resources/lv95.tif").unwrap();
+    let utm = Crs::Projected(ProjectedCrs {
+        linear_unit: Unit::metre(),
+        ..projected_crs(
+            6378137.0,
+            298.257223563,
+            Projection::TransverseMercator {
+                latitude_of_origin: 0.0,
+                longitude_of_origin: 9.0,
+                scale_factor: 0.9996,
+                false_easting: 500000.0,
+                false_northing: 0.0,
+            },
+            1.0,
+        )
+    });
+    let (e, n) = utm.from_wgs84(9.0, 45.0).unwrap();
+    assert!((e - 500000.0).abs() < 1e-6 && (n - 4982950.40).abs() < 0.01);
+
+    // Swiss LV95, compared to the approximate formulas of swisstopo (accurate to about a metre).
+    let lv95 = x.get_crs().unwrap();
+    let (e, n) = lv95
+        .from_wgs84(dms(8.0, 43.0, 49.79), dms(46.0, 2.0, 38.87))
+        .unwrap();
+    assert!((e - 2699999.76).abs() < 1.0 && (n - 1099999.97).abs() < 1.0);
+    let (lon, lat) = lv95.to_wgs84(e, n).unwrap();
+    assert!((lon - dms(8.0, 43.0, 49.79)).abs() < 1e-8);
+    assert!((lat - dms(46.0, 2.0, 38.87)).abs() < 1e-8);
+
+    // The centre of the top left pixel.
+    let (lon, lat) = lv95.to_wgs84(2599500.0, 1200500.0).unwrap();
+    assert_eq!(
+        x.get_value_at_lonlat(lon, lat),
+        x.get_value_of_image_at(0, 0, 0)
+    );
+    assert_eq!(x.get_value_at_lonlat(lon + 0.1, lat), None);
+
+    // Datums without a known transformation to WGS 84.
+    assert!(Crs::Epsg(31467).from_wgs84(9.0, 45.0).is_err());
+    let x = TIFF::open("resources/zh_dem_25.tif").unwrap();
+    assert_eq!(x.get_value_at_lonlat(8.5, 47.4), None);
+}
+
+#[test]
+fn test_raster() {
+    let x = TIFF::open("resources/zh_dem_25.tif").unwrap();
+    assert_eq!(x.image_data().unwrap().sample_type(), SampleType::I16);
+    assert_eq!(x.image_data().unwrap().bands(), 1);
+    match *x.image_data().unwrap() {
+        RasterData::I16(ref raster) => {
+            assert_eq!(raster.data().len(), 399 * 366);
+            assert_eq!(raster.get(67, 45, 0), Some(530));
+            assert_eq!(raster.row(142).unwrap()[325], 587);
+            assert_eq!(raster.pixel(0, 0), Some(&[551][..]));
+            assert_eq!(raster.get(399, 0, 0), None);
+            assert_eq!(raster.get(0, 366, 0), None);
+        }
+        ref data => panic!("Unexpected raster {:?}", data.sample_type()),
+    }
+
+    let nested = x.image_data().unwrap().to_nested();
+    assert_eq!(nested.len(), 366);
+    assert_eq!(nested[0].len(), 399);
+    assert_eq!(nested[45][67], vec![Sample::I16(530)]);
+}
+
+/// Checks that every window of `x` contains the same samples as the whole image.
+fn assert_window_matches(x: &TIFF, x_off: usize, y_off: usize, width: usize, height: usize) {
+    let image = x.image_data().unwrap();
+    let bands: Vec<_> = (0..image.bands()).rev().collect();
+    let window = x.read_window(x_off, y_off, width, height, &bands).unwrap();
+    assert_eq!(window.width(), width);
+    assert_eq!(window.height(), height);
+    assert_eq!(window.bands(), bands.len());
+    for y in 0..height {
+        for x in 0..width {
+            for (i, &band) in bands.iter().enumerate() {
+                assert_eq!(
+                    window.get(x, y, i),
+                    image.get(x_off + x, y_off + y, band),
+                    "Sample {} of pixel ({}, {})",
+                    band,
+                    x_off + x,
+                    y_off + y
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_read_window() {
+    // Strips, tiles (including the padded ones at the border), and an RGB image.
+    for path in [
+        "resources/zh_dem_25.tif",
+        "resources/zh_dem_25_lzw_tiled.tif",
+        "resources/zh_dem_25_lzw_predictor.tif",
+    ] {
+        let x = TIFF::open(path).unwrap();
+        assert_window_matches(&x, 0, 0, 399, 366);
+        assert_window_matches(&x, 190, 120, 60, 40);
+        assert_window_matches(&x, 398, 365, 1, 1);
+    }
+    let x = TIFF::open("resources/marbles.tif").unwrap();
+    assert_window_matches(&x, 700, 490, 20, 30);
+    let window = x.read_window(456, 123, 1, 1, &[1]).unwrap();
+    assert_eq!(window.pixel(0, 0).unwrap(), vec![Sample::U8(61)]);
+
+    // Only the strips of the window are read, so the beginning of a truncated file is fine.
+    let x = TIFF::open("resources/zh_dem_25_truncated.tif").unwrap();
+    let window = x.read_window(67, 45, 1, 1, &[0]).unwrap();
+    assert_eq!(window.get(0, 0, 0), Some(Sample::I16(530)));
+    assert!(x.read_window(0, 300, 10, 10, &[0]).is_err());
+    assert_eq!(x.read_value_at(67, 45).unwrap(), Some(Sample::I16(530)));
+    assert!(x.read_value_at(0, 300).is_err());
+    assert_eq!(x.read_value_at(399, 0).unwrap(), None);
+    assert_eq!(x.read_value_at(0, 366).unwrap(), None);
+    let (model_x, model_y) = x.pixel_to_model(67.5, 45.5).unwrap();
+    assert_eq!(
+        x.get_value_at_coord(model_x, model_y),
+        Some(Sample::I16(530))
+    );
+
+    let x = TIFF::open("resources/zh_dem_25.tif").unwrap();
+    for (x_off, y_off, width, height, bands) in [
+        (0, 0, 0, 10, &[0][..]),
+        (0, 0, 10, 10, &[][..]),
+        (390, 0, 10, 10, &[0][..]),
+        (0, 360, 10, 10, &[0][..]),
+        (0, 0, 10, 10, &[1][..]),
+        (usize::MAX, 0, 10, 10, &[0][..]),
+    ] {
+        match x.read_window(x_off, y_off, width, height, bands) {
+            Err(GeoTiffError::InvalidWindow(_)) => {}
+            other => panic!("Expected an invalid window, got {:?}", other.map(|_| ())),
+        }
+    }
+}
+
+#[test]
+fn test_sampler() {
+    for path in [
+        "resources/zh_dem_25.tif",
+        "resources/zh_dem_25_lzw_tiled.tif",
+        "resources/zh_dem_25_lzw_predictor.tif",
+        "resources/marbles.tif",
+    ] {
+        let x = TIFF::open(path).unwrap();
+        let image = x.image_data().unwrap();
+        // A cache too small for even a single block, and one for a few blocks.
+        for cache_size in [0, 1 << 16] {
+            let mut sampler = x.sampler(cache_size).unwrap();
+            assert_eq!(sampler.width(), image.width());
+            assert_eq!(sampler.height(), image.height());
+            assert_eq!(sampler.bands(), image.bands());
+            // Scattered pixels, going back and forth between blocks.
+            for i in 0..500 {
+                let x = (i * 7919) % image.width();
+                let y = (i * 104729) % image.height();
+                let band = i % image.bands();
+                assert_eq!(
+                    sampler.get(x, y, band).unwrap(),
+                    image.get(x, y, band),
+                    "{}: sample {} of pixel ({}, {})",
+                    path,
+                    band,
+                    x,
+                    y
+                );
+            }
+            assert_eq!(sampler.get(image.width(), 0, 0).unwrap(), None);
+            assert_eq!(sampler.get(0, image.height(), 0).unwrap(), None);
+            assert_eq!(sampler.get(0, 0, image.bands()).unwrap(), None);
+        }
+    }
+
+    // Only the strips of the requested pixels are read.
+    let x = TIFF::open("resources/zh_dem_25_truncated.tif").unwrap();
+    let mut sampler = x.sampler(1 << 20).unwrap();
+    assert_eq!(sampler.get(67, 45, 0).unwrap(), Some(Sample::I16(530)));
+    assert!(sampler.get(0, 365, 0).is_err());
+}
+
+/// Checks that every block of the in-memory TIFF `x` can be viewed, matching the image data.
+/// The samples are little-endian, so they can only be viewed on little-endian hosts.
+#[cfg(target_endian = "little")]
+fn assert_blocks_match(x: &TIFF) {
+    let image = x.image_data().unwrap();
+    let count = x.block_count().unwrap();
+    for index in 0..count {
+        let block = x.view_block::<i16>(index).unwrap().unwrap();
+        assert_eq!(
+            block.samples.len(),
+            block.width * block.height * block.bands
+        );
+        for (i, sample) in block.samples.iter().enumerate() {
+            let (col, row) = (i % block.width, i / block.width);
+            if let Some(expected) = image.get(block.x + col, block.y + row, 0) {
+                assert_eq!(Sample::I16(*sample), expected);
+            }
+        }
+    }
+    match x.view_block::<i16>(count) {
+        Err(GeoTiffError::InvalidWindow(_)) => {}
+        other => panic!("Expected an invalid window, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[cfg(target_endian = "little")]
+#[test]
+fn test_view_block() {
+    let bytes = fs::read("resources/zh_dem_25.tif").unwrap();
+    let x = TIFF::from_bytes(bytes).unwrap();
+    assert_blocks_match(&x);
+    // Only the matching sample type can be viewed.
+    assert!(x.view_block::<u16>(0).unwrap().is_none());
+    // In-memory samples are read in place.
+    let mut sampler = x.sampler(0).unwrap();
+    assert_eq!(sampler.get(67, 45, 0).unwrap(), Some(Sample::I16(530)));
+    assert_eq!(sampler.get(325, 142, 0).unwrap(), Some(Sample::I16(587)));
+
+    // Compressed blocks, and TIFFs read from a file, can't be viewed.
+    let bytes = fs::read("resources/zh_dem_25_lzw.tif").unwrap();
+    let x = TIFF::from_bytes(bytes).unwrap();
+    assert!(x.view_block::<i16>(0).unwrap().is_none());
+    let x = TIFF::open("resources/zh_dem_25.tif").unwrap();
+    assert!(x.view_block::<i16>(0).unwrap().is_none());
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn test_mmap() {
+    // The file is not modified by any test.
+    let x = unsafe { TIFF::open_mmap("resources/zh_dem_25.tif") }.unwrap();
+    assert_eq!(x.get_value_at(45, 67).unwrap(), Some(Sample::I16(530)));
+    #[cfg(target_endian = "little")]
+    assert_blocks_match(&x);
+    assert_window_matches(&x, 190, 120, 60, 40);
+
+    let x = unsafe { TIFF::open_mmap("resources/zh_dem_25_lzw_tiled.tif") }.unwrap();
+    assert_eq!(x.get_value_at(142, 325).unwrap(), Some(Sample::I16(587)));
+    assert_window_matches(&x, 190, 120, 60, 40);
+}
+
+/// Serves the file at `path` on a local port, standing in for an object storage, and counts the
+/// requests. Unless `ranges` is set, the `Range` header is ignored and the whole file is sent.
+#[cfg(feature = "http")]
+fn serve(
+    path: &'static str,
+    ranges: bool,
+) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+    use std::io::{BufRead, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    let bytes = fs::read(path).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/{}", listener.local_addr().unwrap(), path);
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            counter.fetch_add(1, Ordering::SeqCst);
+            let mut target = String::new();
+            let mut range = None;
+            let mut request = BufReader::new(&stream);
+            let mut line = String::new();
+            loop {
+                line.clear();
+                request.read_line(&mut line).unwrap();
+                let lower = line.to_ascii_lowercase();
+                if line == "\r\n" || line.is_empty() {
+                    break;
+                } else if let Some(value) = lower.strip_prefix("get ") {
+                    target = value.split(' ').next().unwrap().to_string();
+                } else if let Some(value) = lower.strip_prefix("range: bytes=") {
+                    let (first, last) = value.trim().split_once('-').unwrap();
+                    let first: usize = first.parse().unwrap();
+                    let last: usize = last.parse().unwrap();
+                    range = Some((first, last.min(bytes.len() - 1)));
+                }
+            }
+            let (head, body) = if target != format!("/{}", path) {
+                ("HTTP/1.1 404 Not Found\r\n".to_string(), &[][..])
+            } else if let (true, Some((first, last))) = (ranges, range) {
+                (
+                    format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\n",
+                        first,
+                        last,
+                        bytes.len()
+                    ),
+                    &bytes[first..=last],
+                )
+            } else {
+                ("HTTP/1.1 200 OK\r\n".to_string(), &bytes[..])
+            };
+            write!(
+                stream,
+                "{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                head,
+                body.len()
+            )
+            .unwrap();
+            stream.write_all(body).unwrap();
+        }
+    });
+    (url, requests)
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_open_url() {
+    use std::sync::atomic::Ordering;
+
+    let (url, requests) = serve("resources/zh_dem_25_lzw_tiled.tif", true);
+    let x = TIFF::open_url(&url).unwrap();
+    let local = TIFF::open("resources/zh_dem_25_lzw_tiled.tif").unwrap();
+    assert_eq!(x.to_string(), local.to_string());
+    // The IFD is at the end of this file, so it takes another request or two.
+    let header_requests = requests.load(Ordering::SeqCst);
+    assert!(header_requests <= 3);
+
+    // The tiles of a window are fetched together.
+    let window = x.read_window(100, 100, 200, 200, &[0]).unwrap();
+    assert_eq!(window, local.read_window(100, 100, 200, 200, &[0]).unwrap());
+    assert_eq!(requests.load(Ordering::SeqCst), header_requests + 1);
+    // Its tiles are kept, so reading within it again takes no requests.
+    x.read_window(150, 150, 100, 100, &[0]).unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), header_requests + 1);
+    assert_window_matches(&x, 190, 120, 60, 40);
+    assert_eq!(x.get_value_at(142, 325).unwrap(), Some(Sample::I16(587)));
+
+    // Without support for range requests, the whole file is downloaded.
+    let (url, requests) = serve("resources/zh_dem_25.tif", false);
+    let x = TIFF::open_url(&url).unwrap();
+    assert_window_matches(&x, 190, 120, 60, 40);
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    match TIFF::open_url(&format!("{}.missing", url)) {
+        Err(GeoTiffError::Io(_)) => {}
+        other => panic!("Expected an I/O error, got {:?}", other.map(|_| ())),
+    }
+}